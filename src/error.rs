@@ -1,11 +1,11 @@
 use std::fmt::Display;
 
-use crate::scanner::error::ScannerError;
+use crate::{expr::parser::ParseError, scanner::error::ScannerError, token::span::Span};
 
 pub enum Error<'a> {
     IO(std::io::Error),
-    Scanner(ScannerError<'a>),
-    Parser((usize, &'a str)),
+    Scanner(ScannerError),
+    Parser(Span, &'a str),
 }
 
 impl From<std::io::Error> for Error<'_> {
@@ -14,19 +14,26 @@ impl From<std::io::Error> for Error<'_> {
     }
 }
 
-impl From<ScannerError<'_>> for Error<'_> {
+impl From<ScannerError> for Error<'_> {
     fn from(value: ScannerError) -> Self {
         Error::Scanner(value)
     }
 }
 
+impl<'a> From<&'a ParseError> for Error<'a> {
+    fn from(value: &'a ParseError) -> Self {
+        Error::Parser(value.span, &value.msg)
+    }
+}
+
 impl Display for Error<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Error::IO(e) => write!(f, "{e}"),
-            Error::Scanner(ScannerError::Err(line, message)) | Error::Parser((line, message)) => {
-                write!(f, "{message} at {line}")
+            Error::Scanner(ScannerError::Err(line, col, message)) => {
+                write!(f, "{message} at {line}:{col}")
             }
+            Error::Parser(span, message) => write!(f, "{message} at {}:{}", span.line, span.col),
         }
     }
 }
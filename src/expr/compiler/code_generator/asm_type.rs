@@ -3,7 +3,7 @@ pub enum AsmTarget {
 }
 #[derive(Clone)]
 pub enum AsmLanguage {
-    NASM
+    Nasm
 }
 
 #[derive(PartialEq, Clone)]
@@ -13,10 +13,20 @@ pub enum NASMRegBase {
     C,
     D
 }
+// sse registers, allocated apart from the general purpose ones
+#[derive(PartialEq, Clone)]
+pub struct NASMXmmReg(pub u8);
+impl NASMXmmReg {
+    pub fn all() -> Vec<Self> {
+        (0..8).map(NASMXmmReg).collect()
+    }
+    pub fn to_str(&self) -> String {
+        format!("xmm{}", self.0)
+    }
+}
 #[derive(Clone, PartialEq)]
 pub enum NASMRegSize {
     L8,
-    H8,
     L16,
     L32,
     L64
@@ -31,7 +41,6 @@ impl NASMRegBase {
         };
         match size {
             NASMRegSize::L8 => format!("{}l", base),
-            NASMRegSize::H8 => format!("{}h", base),
             NASMRegSize::L16 => format!("{}x", base),
             NASMRegSize::L32 => format!("e{}x", base),
             NASMRegSize::L64 => format!("r{}x", base),
@@ -39,10 +48,18 @@ impl NASMRegBase {
     }
 }
 impl NASMRegSize {
+    // the smallest register part that holds a value of size bytes
+    pub fn from_size(size: u32) -> Self {
+        match size {
+            1 => NASMRegSize::L8,
+            2 => NASMRegSize::L16,
+            3 | 4 => NASMRegSize::L32,
+            _ => NASMRegSize::L64,
+        }
+    }
     pub fn to_num(&self) -> usize {
         match self {
             NASMRegSize::L8 => 1,
-            NASMRegSize::H8 => 1,
             NASMRegSize::L16 => 2,
             NASMRegSize::L32 => 4,
             NASMRegSize::L64 => 8,
@@ -51,7 +68,6 @@ impl NASMRegSize {
     pub fn to_name(&self) -> &str {
         match self {
             NASMRegSize::L8 => "byte",
-            NASMRegSize::H8 => "byte",
             NASMRegSize::L16 => "word",
             NASMRegSize::L32 => "dword",
             NASMRegSize::L64 => "qword",
@@ -1,14 +1,19 @@
 use std::{collections::HashMap, ffi::CString, io::Write};
-use crate::{expr::parser::Parser, linker::j_link, token::{literal::Literal, scanner::Scanner}};
-use super::{Expr, Environment};
+use crate::{expr::{module::Module, parser::Parser}, linker::j_link, scanner::Scanner, token::{TokenType, literal::Literal}};
+use super::{DType, Expr, Environment, core_lib::TYPE_BYTES, fill_slice_with_vec, interpreter::Interpret};
 
 pub mod asm_type;
-use asm_type::{AsmLanguage, AsmTarget, NASMRegSize, NASMRegBase};
+use asm_type::{AsmLanguage, AsmTarget, NASMRegSize, NASMRegBase, NASMXmmReg};
 
 pub struct CodeGenerator {
     code_vec: Vec<Code>,
     cur_code: Code,
-    available_regs: Vec<NASMRegBase>
+    available_regs: Vec<NASMRegBase>,
+    available_xmm_regs: Vec<NASMXmmReg>,
+    // labels generated so far, so each one is unique
+    label_count: usize,
+    // the code of each fn declared in a module, by `module:name`
+    decls: HashMap<String, usize>
 }
 #[derive(Clone)]
 struct Code {
@@ -29,13 +34,17 @@ impl CodeGenerator {
                 NASMRegBase::B,
                 NASMRegBase::C,
                 NASMRegBase::D,
-            ]
+            ],
+            available_xmm_regs: NASMXmmReg::all(),
+            label_count: 0,
+            decls: HashMap::new()
         }
     }
 
-    pub fn generate_ir(mut self, ast: Expr, out_path: String, target: AsmTarget, env: &mut Environment) {
+    // modules come after the modules they import, with the entry module last
+    pub fn generate_ir(mut self, modules: Vec<(Module, Environment)>, out_path: String, target: AsmTarget) {
         // generate code
-        self.generate_code(ast, target, env);
+        self.generate_code(modules, target);
         // temp: print out generated assembly
         for (i, code) in self.code_vec.iter().enumerate() {
             let mut code_str = String::new();
@@ -56,13 +65,29 @@ impl CodeGenerator {
         }
     }
     
-    pub fn generate_code(&mut self, ast: Expr, _target: AsmTarget, env: &mut Environment) {
-        // turn ast into assembly
-        self.gen_nasm(ast, env, Some(&NASMRegBase::A));
+    pub fn generate_code(&mut self, modules: Vec<(Module, Environment)>, _target: AsmTarget) {
+        // f0 is the entry point, which jumps to main once we know where main is
+        self.code_vec.push(Code::new(self.cur_code.lang.clone()));
+        let mut entry = 0;
+        let entry_module = modules.len().saturating_sub(1);
+        // turn each declaration into assembly
+        for (i, (module, mut env)) in modules.into_iter().enumerate() {
+            for decl in module.decls {
+                let is_fn = matches!(decl.value(), Expr::Fn(_, _, _));
+                self.gen_nasm(decl.expr, &mut env, Some(&NASMRegBase::A));
+                // a fn literal is the last fn generated
+                if is_fn { self.decls.insert(format!("{}:{}", i, decl.name.lexeme), self.code_vec.len() - 1); }
+                // the checker makes sure main is a fn literal
+                if i == entry_module && decl.name.lexeme == Module::ENTRY { entry = self.code_vec.len() - 1 }
+            }
+        }
+        self.code_vec[0].asm = format!("jmp f{}\n", entry).into_bytes();
     }
 
     pub fn gen_nasm(&mut self, ast: Expr, env: &mut Environment, reg_opt: Option<&NASMRegBase>) -> Option<NASMRegSize> { // TODO
         match ast {
+            // a fn type is only known at compile time
+            Expr::Binary(_, op, _) if op.ttype == TokenType::RightArrow => None,
             Expr::Binary(_, _, right) => {
                 let register = match reg_opt {
                     Some(register) => register.clone(),
@@ -85,77 +110,78 @@ impl CodeGenerator {
             },
             Expr::MsgEmission(_, name, _) => panic!("unexpected msg emission in checked ast: {}", name.lexeme),
             Expr::BinaryOpt(_, _, _) => todo!(), // leave as todo for a while because mostly unnecessary
-            Expr::Asm(_asm_type, _ret_type, text_expr) => { // 2 future TODO
+            Expr::Asm(_asm_type, mut ret_type, text_expr, span) => { // 2 future TODO
                 // TODO: handle different assembly types
                 let mut text = match *text_expr {
-                    Expr::Literal(Literal::String(string)) => string,
-                    _ => panic!("checked asm node does not have string literal as text")
+                    Expr::Literal(Literal::String(bytes, _), _) => String::from_utf8(bytes).expect("checked asm text is not UTF-8"),
+                    _ => panic!("checked asm node does not have string literal as text at {}", span)
                 };
 
+                // a returned value fills as much of the register as its type needs
+                let val_size = asm_ret_type(&mut ret_type, env).map_or(NASMRegSize::L64, |dtype| NASMRegSize::from_size(dtype.size));
+
+                // TODO: we're forced to run the scanner and parser twice, which might not be an
+                // issue since expressions are usually pretty small, but still would probably be best
+                // to have some sort of caching for this, maybe just give in and make a new data structure.
+                // handle embedded jovis expressions
+                while let Some(i) = text.find("j#") {
+                    let mut scanner  = Scanner::new(text.get((i+2)..).unwrap().to_string());
+                    let tokens = scanner.scan_tokens_err_ignore();
+                    let mut parser = Parser::new(tokens);
+                    let (expr, last_token) = parser.parse_and_last_token()
+                        .expect("embedded expression should have been checked");
+                    let n = last_token.span.end + 2;
+
+                    // generate code from checked expr, and put it before the line with the jovis expression
+                    let line_start = text[..i].rfind('\n').map_or(0, |l| l + 1);
+                    let code_start = self.cur_code.asm.len();
+                    let register = self.free_reg(reg_opt);
+                    self.gen_nasm(expr, env, Some(&register));
+                    let expr_code = String::from_utf8(self.cur_code.asm.split_off(code_start))
+                        .expect("generated code should be utf-8");
+                    // an expression on a line of its own is only run, otherwise replace it with its register
+                    let line_end = text[(i+n)..].find('\n').map_or(text.len(), |l| i + n + l + 1);
+                    if text[line_start..i].trim().is_empty() && text[(i+n)..line_end].trim().is_empty() {
+                        text = format!("{}{}{}", &text[..line_start], expr_code, &text[line_end..]);
+                    } else {
+                        text = format!("{}{}{}{}{}",
+                            &text[..line_start], expr_code, &text[line_start..i], register.to_str(NASMRegSize::L64), &text[(i+n)..]
+                        );
+                    }
+                }
+
                 // handle return expressions
                 let mut is_ptr = Some(NASMRegSize::L64);
-                for (i,_) in text.clone().match_indices("jret#") {
+                // embedded expressions are already replaced, so their own returns are gone
+                while let Some(i) = text.find("jret#") {
                     let ret_text = text.get((i+5)..).expect("value of i+5 seems to be too large");
                     let (mut n, is_ptr_in) = if let Some((i, _)) = ret_text.match_indices("addr(").next() {
                         (i+5, None)
                     } else if let Some((i, _)) = ret_text.match_indices("val(").next() {
-                        (i+4, Some(NASMRegSize::L64))
-                    } else { panic!("expected 'addr' or 'val'") };
+                        (i+4, Some(val_size.clone()))
+                    } else { panic!("expected 'addr' or 'val' at {}", span) };
 
                     let mut operand = "".to_string();
                     loop {
                         let c = match ret_text.chars().nth(n) {
                             Some(c) => c,
-                            None => panic!("unterminated jret expression in asm"), // TODO: should be error for user
+                            None => panic!("unterminated jret expression in asm at {}", span), // TODO: should be error for user
                         };
                         if c == ')' { break };
                         operand.push(c);
                         n += 1;
                     }
                     // generate code for return
-                    let asm_ret_text;
-                    match &reg_opt {
-                        Some(register) => {
-                            asm_ret_text = format!("mov {}, {}\n", register.to_str(NASMRegSize::L64), operand.trim());
-                        },
-                        None => asm_ret_text = "".to_string(),
-                    }
+                    let asm_ret_text = match &reg_opt {
+                        Some(register) => format!("mov {}, {}\n", register.to_str(NASMRegSize::L64), operand.trim()),
+                        None => "".to_string(),
+                    };
                     // remove return from text
                     text.replace_range(i..(i+5+n+1), asm_ret_text.as_str());
                     // set is_ptr
                     is_ptr = is_ptr_in;
                 }
 
-                // TODO: we're forced to run the scanner and parser twice, which might not be an
-                // issue since expressions are usually pretty small, but still would probably be best
-                // to have some sort of caching for this, maybe just give in and make a new data structure.
-                // handle embedded jovis expressions
-                for (i,_) in text.clone().match_indices("j#") {
-                    let mut scanner  = Scanner::new(text.get((i+2)..).unwrap().to_string());
-                    let tokens = scanner.scan_tokens_err_ignore();
-                    let mut parser = Parser::new(tokens);
-                    let (expr, last_token) = parser.parse_and_last_token();
-                    let n = last_token.start + last_token.lexeme.len() + 2;
-
-                    // get last semicolon before jovis expression, and push everything before that into cur_code
-                    let mut line_start = i;
-                    loop {
-                        if line_start == 0
-                        || text.chars().nth(line_start).unwrap() == '\n'
-                        { break }
-                        line_start -= 1;
-                    }
-                    let preceeding_text = text.get(0..line_start).unwrap().to_string();
-                    text.replace_range(0..line_start, "");
-                    self.cur_code.asm.append(&mut preceeding_text.as_bytes().to_vec());
-                    // generate code from checked expr
-                    let register = self.get_available_reg(reg_opt);
-                    self.gen_nasm(expr, env, Some(&register));
-                    // replace expression in text with register
-                    text.replace_range((i-line_start)..(i-line_start+n), register.to_str(NASMRegSize::L64).as_str());
-                    self.available_regs.push(register);
-                }
-
                 // handle registers
                 let mut regs: HashMap<Option<String>, NASMRegBase> = HashMap::new();
                 let mut offset = 0;
@@ -166,10 +192,10 @@ impl CodeGenerator {
                         "2#" => NASMRegSize::L16,
                         "4#" => NASMRegSize::L32,
                         "8#" => NASMRegSize::L64,
-                        c => panic!("{}", c)
+                        c => panic!("invalid register size {} in asm at {}", c, span)
                     };
-                    let mut chars = text.chars();
-                    if chars.nth(i+6).map(|c| c.is_ascii_alphabetic() || c == '_') == Some(true) {
+                    let mut chars = text[(i+6)..].chars();
+                    if chars.next().map(|c| c.is_ascii_alphabetic() || c == '_') == Some(true) {
                         let mut j = 7;
                         while chars.next().map(|c| c.is_ascii_alphanumeric() || c == '_') == Some(true)
                             { j += 1 }
@@ -184,31 +210,90 @@ impl CodeGenerator {
                         offset += j-reg_str.len();
                         text.replace_range(i..(i+j), reg_str.as_str());
                     } else {
-                        let reg_base = self.pop_available_reg(reg_opt);
+                        // every jreg without an alias is the same register
+                        let reg_base = match regs.get(&None) {
+                            Some(reg) => reg.clone(),
+                            None => self.pop_available_reg(reg_opt),
+                        };
                         regs.insert(None, reg_base.clone());
-                        let reg_str =reg_base.to_str(reg_size);
+                        let reg_str = reg_base.to_str(reg_size);
                         offset += 6-reg_str.len();
                         text.replace_range(i..(i+6), reg_str.as_str());
                     }
                 }
                 for (_, reg) in regs
                     { self.available_regs.push(reg) }
-                
-                // replace semicolons with newlines
-                text = text.replace(';', "\n");
+                // handle sse registers, which never hold a return value
+                let mut xmm_regs: HashMap<String, NASMXmmReg> = HashMap::new();
+                while let Some(i) = text.find("jxmm#") {
+                    let alias_len = text[(i+5)..].find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                        .unwrap_or(text.len() - (i+5));
+                    let alias = text[(i+5)..(i+5+alias_len)].to_owned();
+                    let reg = match xmm_regs.get(&alias) {
+                        Some(reg) => reg.clone(),
+                        None => {
+                            let reg = self.available_xmm_regs.remove(0);
+                            xmm_regs.insert(alias, reg.clone());
+                            reg
+                        },
+                    };
+                    text.replace_range(i..(i+5+alias_len), reg.to_str().as_str());
+                }
+                for (_, reg) in xmm_regs
+                    { self.available_xmm_regs.push(reg) }
+
+                // handle labels, which are unique to this emission of the asm
+                let mut labels: HashMap<String, String> = HashMap::new();
+                while let Some(i) = text.find("jlabel#") {
+                    let alias_len = text[(i+7)..].find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                        .unwrap_or(text.len() - (i+7));
+                    let alias = text[(i+7)..(i+7+alias_len)].to_owned();
+                    let label = labels.entry(alias).or_insert_with(|| {
+                        self.label_count += 1;
+                        format!("l{}", self.label_count)
+                    }).clone();
+                    text.replace_range(i..(i+7+alias_len), label.as_str());
+                }
+                // a fn declared in a module is wherever its code went, which is before anything that
+                // can name it
+                let mut searched = 0;
+                while let Some(i) = text[searched..].find("jdecl#").map(|i| searched + i) {
+                    let decl_len = text[(i+6)..].find(char::is_whitespace).unwrap_or(text.len() - (i+6));
+                    match self.decls.get(&text[(i+6)..(i+6+decl_len)]) {
+                        Some(code) => text.replace_range(i..(i+6+decl_len), &format!("f{}", code)),
+                        None => searched = i + 6,
+                    }
+                }
+                // a loop is where the breaks and continues in the asm embedded in it go. any left
+                // after this are resolved by a loop around this asm
+                while let Some(i) = text.find("jloop#") {
+                    let line_end = text[i..].find('\n').map_or(text.len(), |n| i + n + 1);
+                    let targets: Vec<String> = text[(i+6)..line_end].split_whitespace().map(|s| s.to_owned()).collect();
+                    text.replace_range(i..line_end, "");
+                    // the type checker only lets through a marker with both
+                    if let [continue_label, break_label] = targets.as_slice() {
+                        text = text.replace("jcontinue#", continue_label).replace("jbreak#", break_label);
+                    }
+                }
 
                 // add text to current code object
                 self.cur_code.asm.append(&mut text.as_bytes().to_vec());
-                return is_ptr
+                is_ptr
             },
-            Expr::Object(exprs) => if let Some(register) = reg_opt { // 1 future TODO
-                // TODO: put values together, handle endianness, and push them on together
-                let val_reg = self.pop_available_reg(reg_opt);
-                self.available_regs.retain(|x| x != register );
-                let mut addr_save = format!("mov {}, rsp\n", register.to_str(NASMRegSize::L64)).as_bytes().to_vec();
-                self.cur_code.asm.append(&mut addr_save);
-                for expr in exprs {
-                    if let Some(size) = self.gen_nasm(expr.clone(), env, Some(&val_reg)) {
+            Expr::Import(_, _) => None, // modules have no runtime value
+            Expr::Object(exprs, _) => if let Some(register) = reg_opt { // 1 future TODO
+                // TODO: put values together and push them on together
+                let val_reg = self.free_reg(reg_opt);
+                // the last value is pushed first, so the object is laid out in order from its address
+                for mut expr in exprs.into_iter().rev() {
+                    // an asm value that isn't in a register is copied in from its address
+                    let copy_size = match &mut expr {
+                        Expr::Asm(_, ret_type, _, _) => asm_ret_type(ret_type, env).map(|dtype| dtype.size),
+                        _ => None,
+                    };
+                    let is_ptr = self.gen_nasm(expr.clone(), env, Some(&val_reg));
+                    if let (None, Some(size)) = (&is_ptr, copy_size) { self.gen_copy_to_stack(&val_reg, size) }
+                    if let Some(size) = is_ptr {
                         if let Expr::Binary(_,_,_) = expr {/* do nothing */}
                         else if size != NASMRegSize::L64 {
                             let mut code_str = format!("sub rsp, {}\n", size.to_num());
@@ -221,28 +306,31 @@ impl CodeGenerator {
                         }
                     }
                 }
-                self.available_regs.push(val_reg);
-                self.available_regs.push(register.clone());
+                let mut addr_save = format!("mov {}, rsp\n", register.to_str(NASMRegSize::L64)).as_bytes().to_vec();
+                self.cur_code.asm.append(&mut addr_save);
                 None
             } else { Some(NASMRegSize::L64) },
-            Expr::Fn(_capture_list, expr) => { // TODO capture list, 1 future TODO
+            Expr::Fn(_capture_list, expr, _) => { // 1 future TODO
                 let mut prev_code = self.cur_code.clone();
                 let prev_regs = self.available_regs.clone();
+                let prev_xmm_regs = self.available_xmm_regs.clone();
                 self.available_regs = vec![
                     NASMRegBase::A,
                     NASMRegBase::B,
                     NASMRegBase::C,
                     NASMRegBase::D,
                 ];
+                self.available_xmm_regs = NASMXmmReg::all();
                 
                 self.cur_code = Code::new(self.cur_code.lang.clone());
                 // add initialization code
                 // TODO: make relative to target (64-bit or 32-bit system). currently assumes 64-bit
                 let mut init = "push rbp\n mov rbp, rsp\n".as_bytes().to_vec();
                 self.cur_code.asm.append(&mut init);
-                // TODO: do something with capture list idk
+                // params are read where the caller put them, above the return address and rbp
 
-                self.gen_nasm(*expr, env, reg_opt.clone());
+                // what a fn returns is left in rax
+                self.gen_nasm(*expr, env, Some(&NASMRegBase::A));
                 // add cleanup code
                 let mut deinit = "mov rsp, rbp\n pop rbp\n ret\n".as_bytes().to_vec();
                 self.cur_code.asm.append(&mut deinit);
@@ -255,9 +343,10 @@ impl CodeGenerator {
 
                 self.cur_code = prev_code;
                 self.available_regs = prev_regs;
+                self.available_xmm_regs = prev_xmm_regs;
                 Some(NASMRegSize::L64)
             },
-            Expr::CodeBlock(mut exprs) => { // TODO waiting on type checker for new stack frame
+            Expr::CodeBlock(mut exprs, _) => { // TODO waiting on type checker for new stack frame
                 // TODO: once type checker has nested environments, store stack frame here
                 match exprs.pop() {
                     Some(last_expr) => {
@@ -270,10 +359,10 @@ impl CodeGenerator {
                     None => Some(NASMRegSize::L64),
                 }
             },
-            Expr::Type(_) => None, // leave as None for a while because mostly unnecessary
-            Expr::Literal(lit) => if let Some(register) = reg_opt { // TODO make target specific
+            Expr::Type(_, _) => None, // leave as None for a while because mostly unnecessary
+            Expr::Literal(lit, _) => if let Some(register) = reg_opt { // TODO make target specific
                 match lit {
-                    Literal::String(s) => {
+                    Literal::String(s, _) => {
                         // TODO: make stack register target-specific
                         let mut text = String::new();
                         // in the future store the characters in .rodata
@@ -281,44 +370,19 @@ impl CodeGenerator {
                         // store stack pointer
                         let char_ptr_reg = self.get_available_reg(Some(register));
                         text.push_str(format!("mov {}, rsp\n", char_ptr_reg.to_str(NASMRegSize::L64)).as_str());
-                        // push each character onto the stack
+                        // copy the characters onto the stack, in order
                         // TODO: make chunk size dependant on target
-                        let mut chunks = s.as_bytes().chunks(8);
-                        let last_chunk_opt = chunks.nth_back(0);
-                        // TODO: flip bytes based on endianness
-                        for qword in chunks {
-                            text.push_str("push 0x");
-                            for c in qword {
-                                text.push_str(format!("{:X}", c).as_str());
-                            }
-                            text.push('\n');
-                        }
-                        if let Some(last_chunk) = last_chunk_opt {
-                            let len = last_chunk.len();
-                            let mut offset = 0;
-                            if len != 8 {
-                                text.push_str(format!("sub rsp, {}\n", len).as_str());
-                                if len % 4 < len {
-                                    offset += 4;
-                                    text.push_str(format!("mov dword [{}-{}], 0x", char_ptr_reg.to_str(NASMRegSize::L64), 4).as_str());
-                                    text.push_str(format!("{:X}{:X}{:X}{:X}\n", last_chunk[0],last_chunk[1],last_chunk[2],last_chunk[3]).as_str());
-                                }
-                                if len % 2 < len {
-                                    text.push_str(format!("mov word [{}-{}], 0x", char_ptr_reg.to_str(NASMRegSize::L64), (offset+2)).as_str());
-                                    text.push_str(format!("{:X}{:X}\n", last_chunk[offset],last_chunk[offset + 1]).as_str());
-                                    offset += 2;
-                                }
-                                if offset != len {
-                                    text.push_str(format!("mov byte [{}-{}], 0x", char_ptr_reg.to_str(NASMRegSize::L64), (offset+1)).as_str());
-                                    text.push_str(format!("{:X}\n", last_chunk[offset]).as_str());
-                                }
-                            } else {
-                                text.push_str("push 0x");
-                                for c in last_chunk {
-                                    text.push_str(format!("{:X}", c).as_str());
-                                }
-                                text.push('\n');
-                            }
+                        let bytes = &s;
+                        text.push_str(format!("sub rsp, {}\n", bytes.len()).as_str());
+                        let mut offset = 0;
+                        while offset < bytes.len() {
+                            let (size, val) = match bytes.len() - offset {
+                                1 => (NASMRegSize::L8, bytes[offset] as u32),
+                                2 | 3 => (NASMRegSize::L16, u16::from_le_bytes([bytes[offset], bytes[offset+1]]) as u32),
+                                _ => (NASMRegSize::L32, u32::from_le_bytes([bytes[offset], bytes[offset+1], bytes[offset+2], bytes[offset+3]])),
+                            };
+                            text.push_str(format!("mov {} [rsp+{}], 0x{:X}\n", size.to_name(), offset, val).as_str());
+                            offset += size.to_num();
                         }
                         // store stack pointer in return register
                         text.push_str(format!("mov {}, rsp\n", register.to_str(NASMRegSize::L64)).as_str());
@@ -332,37 +396,75 @@ impl CodeGenerator {
                         None
                     },
                     Literal::Char(c) => {
-                        let mut code = format!("mov {}, 0x{:X}\n", register.to_str(NASMRegSize::L8), c as u8).as_bytes().to_vec();
+                        // chars are stored as their utf-8 encoding, zero-padded to a dword
+                        let mut utf8 = [0; 4];
+                        c.encode_utf8(&mut utf8);
+                        let mut code = format!("mov {}, 0x{:X}\n", register.to_str(NASMRegSize::L32), u32::from_le_bytes(utf8)).as_bytes().to_vec();
                         self.cur_code.asm.append(&mut code);
-                        Some(NASMRegSize::L8)
+                        Some(NASMRegSize::L32)
                     },
                     Literal::Integer(i) => {
                         let mut code = format!("mov {}, 0x{:X}\n", register.to_str(NASMRegSize::L32), i as u32).as_bytes().to_vec();
                         self.cur_code.asm.append(&mut code);
                         Some(NASMRegSize::L32)
                     },
-                    Literal::Float(f) => {
-                        let mut code = format!("mov {}, 0x{:X}\n", register.to_str(NASMRegSize::L32), f as u32).as_bytes().to_vec();
-                        self.cur_code.asm.append(&mut code);
-                        Some(NASMRegSize::L32)
-                    },
+                    // floats travel in general purpose registers as their bits
+                    Literal::Float(f) => self.gen_mov_imm(register, NASMRegSize::L32, f.to_bits() as u64),
                     Literal::Byte(b) => {
                         let mut code = format!("mov {}, 0x{:X}\n", register.to_str(NASMRegSize::L8), b).as_bytes().to_vec();
                         self.cur_code.asm.append(&mut code);
                         Some(NASMRegSize::L8)
                     },
+                    Literal::B16(b) => self.gen_mov_imm(register, NASMRegSize::L16, b as u64),
+                    Literal::B32(b) => self.gen_mov_imm(register, NASMRegSize::L32, b as u64),
+                    Literal::B64(b) => self.gen_mov_imm(register, NASMRegSize::L64, b),
+                    Literal::Bool(b) => self.gen_mov_imm(register, NASMRegSize::L8, b as u64),
+                    Literal::I8(i) => self.gen_mov_imm(register, NASMRegSize::L8, i as u8 as u64),
+                    Literal::I16(i) => self.gen_mov_imm(register, NASMRegSize::L16, i as u16 as u64),
+                    Literal::I64(i) => self.gen_mov_imm(register, NASMRegSize::L64, i as u64),
+                    Literal::U8(u) => self.gen_mov_imm(register, NASMRegSize::L8, u as u64),
+                    Literal::U16(u) => self.gen_mov_imm(register, NASMRegSize::L16, u as u64),
+                    Literal::U32(u) => self.gen_mov_imm(register, NASMRegSize::L32, u as u64),
+                    Literal::U64(u) => self.gen_mov_imm(register, NASMRegSize::L64, u),
+                    Literal::F64(f) => self.gen_mov_imm(register, NASMRegSize::L64, f.to_bits()),
                 }
             } else { Some(NASMRegSize::L64) },
         }
     }
 
-    fn get_available_reg(&mut self, ret_reg: Option<&NASMRegBase>) -> NASMRegBase {
-        match ret_reg {
-            Some(register) => if &self.available_regs[0] == register
-            { self.available_regs.remove(1) }
-            else { self.available_regs.remove(0) },
-            None => { self.available_regs.remove(0) },
+    fn gen_copy_to_stack(&mut self, src: &NASMRegBase, size: u32) {
+        let tmp = self.pop_available_reg(Some(src));
+        let mut code = format!("sub rsp, {}\n", size);
+        let mut copied = 0;
+        while copied < size {
+            let chunk = [NASMRegSize::L64, NASMRegSize::L32, NASMRegSize::L16, NASMRegSize::L8].iter()
+                .find(|chunk| chunk.to_num() as u32 <= size - copied).cloned().unwrap();
+            code.push_str(&format!("mov {}, {} [{}+{}]\nmov {} [rsp+{}], {}\n",
+                tmp.to_str(chunk.clone()), chunk.to_name(), src.to_str(NASMRegSize::L64), copied,
+                chunk.to_name(), copied, tmp.to_str(chunk.clone())
+            ));
+            copied += chunk.to_num() as u32;
         }
+        self.cur_code.asm.append(&mut code.into_bytes());
+        self.available_regs.push(tmp);
+    }
+
+    fn gen_mov_imm(&mut self, register: &NASMRegBase, size: NASMRegSize, imm: u64) -> Option<NASMRegSize> {
+        let mut code = format!("mov {}, 0x{:X}\n", register.to_str(size.clone()), imm).as_bytes().to_vec();
+        self.cur_code.asm.append(&mut code);
+        Some(size)
+    }
+
+    // the return register is only given out when there's no other
+    fn get_available_reg(&mut self, ret_reg: Option<&NASMRegBase>) -> NASMRegBase {
+        let i = self.available_regs.iter().position(|reg| Some(reg) != ret_reg).unwrap_or(0);
+        self.available_regs.remove(i)
+    }
+    // a register for a value that's only used once the code computing it is done, like an
+    // operand that's pushed as soon as it's computed. that code can use it too, so it isn't taken
+    // from the others, and however deep the code is nested no more than the four are needed
+    fn free_reg(&self, ret_reg: Option<&NASMRegBase>) -> NASMRegBase {
+        self.available_regs.iter().find(|reg| Some(*reg) != ret_reg).cloned().unwrap_or(NASMRegBase::A)
     }
     fn pop_available_reg(&mut self, ret_reg: Option<&NASMRegBase>) -> NASMRegBase {
         let reg = self.get_available_reg(ret_reg);
@@ -379,12 +481,12 @@ impl CodeGenerator {
         { addr_size = 8; }
         #[cfg(target_pointer_width = "32")]
         { addr_size = 4; }
-        buf.extend_from_slice(&(0 as usize).to_ne_bytes()); // data ptr
-        buf.extend_from_slice(&(0 as usize).to_ne_bytes()); // data size
-        buf.extend_from_slice(&((6*addr_size as usize) as usize).to_ne_bytes()); // code ptr
-        buf.extend_from_slice(&(fn_no as usize).to_ne_bytes()); // fn no
-        buf.extend_from_slice(&(0 as usize).to_ne_bytes()); // dep ptr
-        buf.extend_from_slice(&(0 as usize).to_ne_bytes()); // dep no
+        buf.extend_from_slice(&0_usize.to_ne_bytes()); // data ptr
+        buf.extend_from_slice(&0_usize.to_ne_bytes()); // data size
+        buf.extend_from_slice(&(6*addr_size as usize).to_ne_bytes()); // code ptr
+        buf.extend_from_slice(&fn_no.to_ne_bytes()); // fn no
+        buf.extend_from_slice(&0_usize.to_ne_bytes()); // dep ptr
+        buf.extend_from_slice(&0_usize.to_ne_bytes()); // dep no
 
         // writen fns
         for code in self.code_vec {
@@ -394,6 +496,103 @@ impl CodeGenerator {
             buf.append(&mut text);
         }
         let mut file = std::fs::File::create(out_path).unwrap();
-        file.write_all(&mut buf).unwrap();
+        file.write_all(&buf).unwrap();
     }
-}
\ No newline at end of file
+}
+
+// the type an asm node says it returns
+fn asm_ret_type(ret_type: &mut Expr, env: &mut Environment) -> Option<DType> {
+    let mut ret_type_slice = [0; TYPE_BYTES];
+    match ret_type.interpret(env) {
+        Some((bytes, _)) if bytes.len() == ret_type_slice.len() => {
+            fill_slice_with_vec(&mut ret_type_slice, bytes);
+            Some(DType::from_bytes(ret_type_slice))
+        },
+        _ => None,
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::loader::Loader;
+    use super::{CodeGenerator, asm_type::{AsmLanguage, AsmTarget}};
+
+    // writes files to a directory of their own and loads the last one, giving the code of each fn
+    fn generate(test: &str, files: &[(&str, &str)]) -> Result<Vec<String>, String> {
+        let dir = std::env::temp_dir().join(format!("jovis-{}-{}", test, std::process::id()));
+        for (name, source) in files.iter() {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+        let mut loader = Loader::new();
+        let loaded = loader.load(&dir.join(files.last().unwrap().0)).map_err(|e| e.to_string());
+        fs::remove_dir_all(&dir).unwrap();
+        loaded?;
+
+        let mut generator = CodeGenerator::new(AsmLanguage::Nasm);
+        generator.generate_code(loader.into_modules(), AsmTarget::X86Unix);
+        Ok(generator.code_vec.iter().map(|code| String::from_utf8(code.asm.clone()).unwrap()).collect())
+    }
+
+    #[test]
+    fn module_fns() {
+        // a module's fns are called by their labels, from fns in it and in the modules importing it
+        let fns = generate("module_fns", &[
+            ("lib/math.jup", "pub: add ;= |a; I32 b; I32| a + b\n"),
+            ("main.jup", "math ;= import: \"lib/math.jup\"\ntwice ;= |x; I32| math.add.!: x, x\n\
+                triple ;= |double ;= twice x; I32| x + double.!: x\nmain ;= ||{ twice.!: { triple.!: 1 } }\n"),
+        ]).unwrap();
+        assert_eq!(fns.len(), 5);
+        assert_eq!(fns[0], "jmp f4\n");
+        assert!(fns[2].contains(", f1\n") && fns[3].contains(", f2\n"), "{:?}", fns);
+        assert!(fns[4].contains(", f2\n") && fns[4].contains(", f3\n"), "{:?}", fns);
+        assert!(fns.iter().all(|code| !code.contains('#')), "{:?}", fns);
+
+        // there's no code for any other runtime value to be put in
+        let err = generate("module_values", &[("main.jup", "x ;= asm [] I32 \"jret# val( rax )\"\nmain ;= ||{ x }\n")]);
+        assert!(err.unwrap_err().ends_with("x is declared in a module, so it has to be a fn or have a static value at 1:6"));
+    }
+
+    #[test]
+    fn nested_operands() {
+        // an operand is pushed as soon as it's computed, so no register is held while the others are
+        let names = |n: usize| (0..n).map(|i| if i % 2 == 0 { "a" } else { "b" }).collect::<Vec<_>>();
+        let left_nested = names(20).join(" + ");
+        let right_nested = format!("{}{}", names(12).join(" - { "), " }".repeat(11));
+        for body in [left_nested.as_str(), &right_nested, "a + b * {a - {b + a}}", "x ;= [[[[[a b]]]]]\n a"].iter() {
+            let source = format!("main ;= |a; I32 b; I32|{{ {} }}\n", body);
+            let fns = generate("nested_operands", &[("main.jup", &source)]).unwrap();
+            assert!(fns.iter().all(|code| !code.contains('#')), "{:?}", fns);
+        }
+    }
+
+    #[test]
+    fn float_literals() {
+        // a float is moved in as its bits, not truncated to an integer
+        let source = "main ;= |a; F32 b; F64|{ x ;= a + 1.5\n b * 2.5f64 }\n";
+        let fns = generate("float_literals", &[("main.jup", source)]).unwrap();
+        let code = fns.concat();
+        assert!(code.contains(", 0x3FC00000\n"), "{}", code);
+        assert!(code.contains(", 0x4004000000000000\n"), "{}", code);
+    }
+
+    #[test]
+    fn control_flow() {
+        // a fn value is called by control structures, the same as with `.!`
+        let source = "main ;= ||{\n c ;= ||{ 1 }\n loop: { c.!\n break }\n loop: c\n while: true, c\n if_else: false, c, { 2 }\n 0 }\n";
+        let fns = generate("control_flow", &[("main.jup", source)]).unwrap();
+        assert!(fns.iter().all(|code| !code.contains('#')), "{:?}", fns);
+        assert_eq!(fns[2].matches("call qword [r11").count(), 4, "{}", fns[2]);
+    }
+
+    #[test]
+    fn calls_survive_syscall() {
+        // the fn clobbers r11, so the caller gets it back from above the args it copied
+        let source = "main ;= ||{\n f ;= |a; I32 b; I64|{ asm [] I32 \" mov r11, 0\\n syscall\\n jret# val( rax )\" }\n f.!: 1, 2i64 }\n";
+        let fns = generate("calls_survive_syscall", &[("main.jup", source)]).unwrap();
+        let main = &fns[2];
+        assert!(main.contains(" sub rsp, 24\n mov qword [rsp+16], r11\n"), "{}", main);
+        assert!(main.contains(" call qword [r11+16]\n mov r11, qword [rsp+16]\n lea rsp, [r11+24]\n"), "{}", main);
+    }
+}
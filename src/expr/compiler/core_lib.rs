@@ -1,13 +1,23 @@
-use std::rc::Rc;
-use crate::token::literal::Literal;
+use std::{path::PathBuf, rc::Rc};
+use crate::{expr::parser::Parser, loader::LoadError, scanner::Scanner};
 
-use super::{DType, Environment, dtype::Msg, Expr};
+use super::{DType, Environment, dtype::Intrinsic};
 
 pub const VOID: DType = DType {
     size: 0,
     msgs: vec![],
     size_unknown: false,
-    msgs_unknown: false
+    msgs_unknown: false,
+    intrinsic: None
+};
+
+// what a decl without a type starts as, until its uses say more
+pub const UNKNOWN: DType = DType {
+    size: 0,
+    msgs: vec![],
+    size_unknown: true,
+    msgs_unknown: true,
+    intrinsic: None
 };
 
 // byte types
@@ -15,126 +25,227 @@ pub const B8: DType = DType {
     size: 1,
     msgs: vec![],
     size_unknown: false,
-    msgs_unknown: false
-};
-// const B16: DType = DType {
-//     size: 2,
-//     msgs: vec![],
-    // size_unknown: false,
-    // msgs_unknown: false
-// };
-// const B32: DType = DType {
-//     size: 4,
-//     msgs: vec![],
-    // size_unknown: false,
-    // msgs_unknown: false
-// };
-// const B64: DType = DType {
-//     size: 8,
-//     msgs: vec![],
-    // size_unknown: false,
-    // msgs_unknown: false
-// };
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::B8)
+};
+pub const B16: DType = DType {
+    size: 2,
+    msgs: vec![],
+    size_unknown: false,
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::B16)
+};
+pub const B32: DType = DType {
+    size: 4,
+    msgs: vec![],
+    size_unknown: false,
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::B32)
+};
+pub const B64: DType = DType {
+    size: 8,
+    msgs: vec![],
+    size_unknown: false,
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::B64)
+};
+
+// the sizes of the compile time values the compiler reads back: a string is the address of its
+// first byte then its length, a type is its u32 size, bools for unknowns, its intrinsic and a u32
+// naming its msg table, and a decl is its name as a string then its type
+pub const STRING_BYTES: usize = 8 + 8;
+pub const TYPE_BYTES: usize = 4 + 1 + 1 + 1 + 4;
+pub const DECL_BYTES: usize = STRING_BYTES + TYPE_BYTES;
 
 // "primitives"
 pub const STRING: DType = DType {
-    size: 16,
+    size: STRING_BYTES as u32,
     msgs: vec![],
     size_unknown: false,
-    msgs_unknown: false
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::String)
 };
-pub fn str_from_jstr(bytes: [u8; 16], env: &mut Environment) -> Option<String> {
-    let mut addr: [u8; 8] = [0; 8]; // TODO: find more efficient way to do this
-    for i in 0..8 {
-        addr[i] = bytes[i];
-    }
-    let mut size: [u8; 8] = [0; 8]; // TODO: find more efficient way to do this
-    for i in 0..8 {
-        size[i] = bytes[i+8];
-    }
+pub fn str_from_jstr(bytes: [u8; STRING_BYTES], env: &mut Environment) -> Option<String> {
+    let mut addr: [u8; 8] = [0; 8];
+    addr.copy_from_slice(&bytes[..8]);
+    let mut size: [u8; 8] = [0; 8];
+    size.copy_from_slice(&bytes[8..]);
     let addr = usize::from_ne_bytes(addr);
     let size = usize::from_ne_bytes(size);
     let val_bytes = env.get_stack(addr);
     match val_bytes {
         Some(val_bytes) => {
             if val_bytes.len() != size { return None }
-            let mut str = String::default();
-            for byte in val_bytes {
-                str.push(*byte as char);
-            }
-            Some(str)
+            String::from_utf8(val_bytes.clone()).ok()
         },
         None => None
     }
 }
 pub const CHAR: DType = DType {
+    size: 4, // utf-8 encoding, zero-padded
+    msgs: vec![],
+    size_unknown: false,
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::Char)
+};
+pub const I8: DType = DType {
     size: 1,
     msgs: vec![],
     size_unknown: false,
-    msgs_unknown: false
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::I8)
+};
+pub const I16: DType = DType {
+    size: 2,
+    msgs: vec![],
+    size_unknown: false,
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::I16)
 };
 pub const I32: DType = DType {
     size: 4,
     msgs: vec![],
     size_unknown: false,
-    msgs_unknown: false
-};
-// pub const U32: DType = DType {
-//     size: 4,
-//     msgs: vec![],
-    // size_unknown: false,
-    // msgs_unknown: false
-// };
-// pub const U64: DType = DType {
-//     size: 8,
-//     msgs: vec![],
-    // size_unknown: false,
-    // msgs_unknown: false
-// };
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::I32)
+};
+pub const I64: DType = DType {
+    size: 8,
+    msgs: vec![],
+    size_unknown: false,
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::I64)
+};
+pub const U8: DType = DType {
+    size: 1,
+    msgs: vec![],
+    size_unknown: false,
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::U8)
+};
+pub const U16: DType = DType {
+    size: 2,
+    msgs: vec![],
+    size_unknown: false,
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::U16)
+};
+pub const U32: DType = DType {
+    size: 4,
+    msgs: vec![],
+    size_unknown: false,
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::U32)
+};
+pub const U64: DType = DType {
+    size: 8,
+    msgs: vec![],
+    size_unknown: false,
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::U64)
+};
 pub const F32: DType = DType {
     size: 4,
     msgs: vec![],
     size_unknown: false,
-    msgs_unknown: false
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::F32)
+};
+pub const F64: DType = DType {
+    size: 8,
+    msgs: vec![],
+    size_unknown: false,
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::F64)
+};
+// 0 or 1
+pub const BOOL: DType = DType {
+    size: 1,
+    msgs: vec![],
+    size_unknown: false,
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::Bool)
 };
-// pub const BOOL: DType = DType {
-//     size: 1,
-//     msgs: vec![],
-//     size_unknown: false,
-//     msgs_unknown: false
-// };
 pub const TYPE: DType = DType {
-    size: 6, // stores u32 size, which will temporarily be the only thing a udt represents, and bools for unknowns
+    size: TYPE_BYTES as u32,
     msgs: vec![],
     size_unknown: false,
-    msgs_unknown: false
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::Type)
 };
 pub const FN: DType = DType {
     size: 8, // u64 of address
     msgs: vec![],
     size_unknown: false,
-    msgs_unknown: false
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::Fn)
+};
+// the fn's address, then the address of the record of what it captured
+pub const CLOSURE: DType = DType {
+    size: 16,
+    msgs: vec![],
+    size_unknown: false,
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::Closure)
 };
 pub const DECL: DType = DType {
-    size: 22, // name as string then type
+    size: DECL_BYTES as u32,
     msgs: vec![],
     size_unknown: false,
-    msgs_unknown: false
+    msgs_unknown: false,
+    intrinsic: Some(Intrinsic::Decl)
 };
 
-pub fn export() -> DType {
-    DType {
-        size: 0,
-        msgs: vec![
-            {
-                let byte_lits: Vec<Expr> = I32.to_bytes().iter().map(|byte|
-                    { Expr::Literal(Literal::Byte(*byte)) }).collect();
-                let constructor = move |_: Option<Box<Expr>>, _: &Environment, _: Option<Box<Expr>>|
-                { Expr::Object(byte_lits.clone()) };
-                Msg::new("I32".into(), Rc::new(constructor), TYPE, None)
-            }
-        ],
-        size_unknown: false,
-        msgs_unknown: false
+// the directories in JOVIS_PATH, searched for a prelude.jup to use instead of the bundled one
+pub fn search_path() -> Vec<PathBuf> {
+    std::env::var_os("JOVIS_PATH").map_or(vec![], |paths| std::env::split_paths(&paths).collect())
+}
+
+thread_local! {
+    // checking the prelude is the same work every time, so only do it once
+    static PRELUDE: Result<(DType, DType), Rc<LoadError>> = load_prelude().map_err(Rc::new);
+}
+
+// the prelude's exports, as (runtime, compile time) stack types, or why it couldn't be loaded
+pub fn prelude() -> Result<(DType, DType), Rc<LoadError>> {
+    PRELUDE.with(|prelude| prelude.clone())
+}
+// the loader reports a prelude that can't be loaded before checking anything, so without one
+// there's only `intrinsic:`
+pub fn export() -> (DType, DType) {
+    prelude().unwrap_or_else(|_| (Environment::empty().get_rt_stack_type(), Environment::empty().get_ct_stack_type()))
+}
+
+fn load_prelude() -> Result<(DType, DType), LoadError> {
+    match search_path().into_iter().map(|dir| dir.join("prelude.jup")).find(|path| path.is_file()) {
+        Some(path) => {
+            let source = std::fs::read_to_string(&path).map_err(|e| LoadError::IO(path.clone(), e))?;
+            check_prelude(path, source)
+        },
+        None => check_prelude(PathBuf::from("bundled prelude"), include_str!("prelude.jup").to_string()),
+    }
+}
+fn check_prelude(path: PathBuf, source: String) -> Result<(DType, DType), LoadError> {
+    let (tokens, errors) = Scanner::new(source).scan_tokens_recover();
+    if !errors.is_empty() { return Err(LoadError::Scanner(path, errors)) }
+    let mut module = Parser::new(tokens).parse_module().map_err(|errors| LoadError::Parser(path.clone(), errors))?;
+    let mut env = Environment::empty();
+    module.check(&mut env).map_err(|e| LoadError::Type(path, e))?;
+
+    Ok((module.export_type(env.get_rt_stack_type()), module.export_type(env.get_ct_stack_type())))
+}
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use super::check_prelude;
+
+    #[test]
+    fn prelude_errors() {
+        // a prelude that can't be loaded is reported at where it went wrong, like any module
+        let error = |source: &str| check_prelude(PathBuf::from("lib/prelude.jup"), source.to_string()).unwrap_err().to_string();
+        assert_eq!(error("pub: I32 ;= intrinsic: \"I32\"\n x ;= 'ab'"), "lib/prelude.jup: Oversized character literal at 2:7");
+        assert_eq!(error("pub: I32 ;= intrinsic: \"I32\"\nx ;= )"), "lib/prelude.jup: Invalid expression-starting token at 2:6");
+        assert_eq!(error("pub: I32 ;= intrinsic: \"I33\""), "lib/prelude.jup: err: incorrect type of constructed expression at 1:13");
+        assert!(check_prelude(PathBuf::from("bundled prelude"), include_str!("prelude.jup").to_string()).is_ok());
     }
-}
\ No newline at end of file
+}
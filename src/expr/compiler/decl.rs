@@ -1,84 +1,90 @@
 use std::rc::Rc;
-use crate::token::literal::Literal;
-use super::{Expr, TypeCheck, core_lib::*, dtype::{DType, Msg}, env::Environment, fill_slice_with_vec, interpreter::Interpret, type_checker::TypeError};
+use crate::token::{literal::Literal, span::Span};
+use super::{Expr, TypeCheck, core_lib::*, dtype::{DType, Msg}, env::Environment, fields, fill_slice_with_vec, infer::TypeVar, interpreter::Interpret, intrinsics::asm_node, type_checker::TypeError};
 
 pub struct Decl {
     pub name: String,
     pub dtype: DType
 }
 impl Decl {
-    pub fn from_bytes(bytes: [u8; 22], env: &mut Environment) -> Option<Self> {
-        let mut name_bytes = [0; 16]; // TODO: find more efficient way to do this
-        for i in 0..16 {
-            name_bytes[i] = bytes[i];
-        }
+    pub fn from_bytes(bytes: [u8; DECL_BYTES], env: &mut Environment) -> Option<Self> {
+        let mut name_bytes = [0; STRING_BYTES];
+        name_bytes.copy_from_slice(&bytes[..STRING_BYTES]);
         let name = str_from_jstr(name_bytes, env)?;
 
-        let mut type_bytes = [0; 6]; // TODO: find more efficient way to do this
-        for i in 0..6 {
-            type_bytes[i] = bytes[i+16];
-        }
+        let mut type_bytes = [0; TYPE_BYTES];
+        type_bytes.copy_from_slice(&bytes[STRING_BYTES..]);
         let dtype = DType::from_bytes(type_bytes);
 
         Some(Self { name, dtype })
     }
     pub fn from_expr(expr: &mut Expr, env: &mut Environment) -> Option<Self> {
-        let mut decl_slice = [0; 22];
+        let mut decl_slice = [0; DECL_BYTES];
         let expr_bytes = expr.interpret(env)?.0;
         fill_slice_with_vec(&mut decl_slice, expr_bytes);
         Decl::from_bytes(decl_slice, env)
     }
 
-    pub fn initialize(&self, val: &mut Expr, env: &mut Environment) -> Result<DType, TypeError> {
+    // a runtime value in a fn gets a slot in its stack frame, and the expr that stores it there
+    pub fn initialize(&self, val: &mut Expr, declared_at: Span, env: &mut Environment) -> Result<(DType, Option<Expr>), TypeError> {
+        let mut type_var = TypeVar::new(&self.name, &self.dtype, declared_at);
+        let val_span = val.span();
         let dtype = val.check(env)?;
-        let final_dtype = self.dtype.union(&dtype)
-            .ok_or(TypeError::new("initialization value does not match declared type".into(), None))?;
-        // println!("{:?} {:?} {:?}", self.dtype, dtype, final_dtype);
+        type_var.constrain("value", &dtype, val_span)?;
+        let final_dtype = type_var.resolve();
+        env.add_type_var(type_var);
 
-        match val.interpret(env) {
-            Some((bytes, ct_dtype)) => {
-                let mut byte_lits = vec![];
-                for byte in bytes {
-                    byte_lits.push(Expr::Literal(Literal::Byte(byte)));
-                }
-                let constructor = move |_: Option<Box<Expr>>, _: &Environment, _: Option<Box<Expr>>|
-                { Expr::Object(byte_lits.clone()) };
-                if ct_dtype == final_dtype {
-                    env.add_rt_msg(Msg::new(self.name.clone(), Rc::new(constructor.clone()), final_dtype.clone(), None));
-                } else {
-                    // add runtime msg TODO: defer code to a function
-                    let constructor = move |_: Option<Box<Expr>>, _: &Environment, _: Option<Box<Expr>>|
-                    { Expr::Object(vec![]) }; // TODO: return asm node
-                    env.add_rt_msg(Msg::new(self.name.clone(), Rc::new(constructor), final_dtype.clone(), None));
-                    env.add_rt_size(final_dtype.size);
-                    println!("rt stack: {:?}", env.get_rt_stack_type());
-                }
-                env.add_ct_msg(Msg::new(self.name.clone(), Rc::new(constructor), ct_dtype, None));
-            },
-            None => {
-                // add runtime msg TODO: defer code to a function
-                let constructor = move |_: Option<Box<Expr>>, _: &Environment, _: Option<Box<Expr>>|
-                { Expr::Object(vec![]) }; // TODO: return asm node
-                env.add_rt_msg(Msg::new(self.name.clone(), Rc::new(constructor), final_dtype.clone(), None));
-                env.add_rt_size(final_dtype.size);
-                println!("rt stack: {:?}", env.get_rt_stack_type());
-            },
+        if let Some((bytes, ct_dtype)) = val.interpret(env) {
+            let mut byte_lits = vec![];
+            for byte in bytes {
+                byte_lits.push(Expr::Literal(Literal::Byte(byte), Span::default()));
+            }
+            let constructor = move |_: Option<Box<Expr>>, _: &Environment, _: Vec<Expr>|
+            { Expr::Object(byte_lits.clone(), Span::default()) };
+            env.add_ct_msg(Msg::new(self.name.clone(), Rc::new(constructor.clone()), ct_dtype.clone(), vec![]));
+            if final_dtype.is_assignable_from(&ct_dtype) {
+                env.add_rt_msg(Msg::new(self.name.clone(), Rc::new(constructor), final_dtype, vec![]));
+                return Ok((dtype, None))
+            }
         }
-
-        Ok(dtype)
+        Ok((dtype, self.add_runtime(val, final_dtype, env)?))
+    }
+    fn add_runtime(&self, val: &Expr, dtype: DType, env: &mut Environment) -> Result<Option<Expr>, TypeError> {
+        if !env.in_fn() {
+            // a module has no code of its own to put a value in, but a fn's code is somewhere, and
+            // its value is that label
+            let label = match (val, env.module()) {
+                (Expr::Fn(_, _, _), Some(module)) => format!("jdecl#{}:{}", module, self.name),
+                _ => return Err(TypeError::at(
+                    format!("{} is declared in a module, so it has to be a fn or have a static value", self.name), val.span()
+                )),
+            };
+            let get_type = dtype.clone();
+            let getter = move |_: Option<Box<Expr>>, _: &Environment, _: Vec<Expr>|
+                asm_node(&get_type, format!(" mov jreg8#f, {}\n jret# val( jreg8#f )\n", label));
+            let msg = Msg::new(self.name.clone(), Rc::new(getter), dtype, vec![]);
+            env.add_rt_msg(msg.clone());
+            env.add_module_decl(msg);
+            return Ok(None)
+        }
+        env.add_rt_size(dtype.size);
+        let offset = env.get_rt_stack_type().size;
+        env.add_rt_msg(fields::local_msg(self.name.clone(), dtype.clone(), offset));
+        env.add_rt_addr(self.name.clone(), fields::local_addr(offset));
+        Ok(Some(fields::local_store(&dtype, offset, val)))
     }
     pub fn ct_initialize(&self, mut val: Expr, env: &mut Environment) -> Option<(Vec<u8>, DType)> {
         let dtype = val.check(env).ok()?;
-        if self.dtype != dtype { return None }
+        if !self.dtype.is_assignable_from(&dtype) { return None }
 
         let (bytes, ct_dtype) = val.interpret(env)?;
         let mut byte_lits = vec![];
         for byte in bytes.clone() {
-            byte_lits.push(Expr::Literal(Literal::Byte(byte)));
+            byte_lits.push(Expr::Literal(Literal::Byte(byte), Span::default()));
         }
-        let constructor = move |_: Option<Box<Expr>>, _: &Environment, _: Option<Box<Expr>>|
-        { Expr::Object(byte_lits.clone()) };
-        env.add_ct_msg(Msg::new(self.name.clone(), Rc::new(constructor), ct_dtype, None));
+        let constructor = move |_: Option<Box<Expr>>, _: &Environment, _: Vec<Expr>|
+        { Expr::Object(byte_lits.clone(), Span::default()) };
+        env.add_ct_msg(Msg::new(self.name.clone(), Rc::new(constructor), ct_dtype, vec![]));
 
         Some((bytes, dtype))
     }
@@ -1,14 +1,16 @@
-use std::{fmt::Debug, rc::Rc};
+use std::{cell::RefCell, fmt::{Debug, Display}, rc::Rc};
 
-use crate::{expr::Expr, token::literal::Literal};
-use super::{Environment, core_lib::*};
+use crate::{expr::Expr, token::{literal::Literal, span::Span}};
+use super::{Environment, core_lib::*, interpreter::Interpret, intrinsics::intrinsic_msgs};
 
 #[derive(Clone, Debug)]
 pub struct DType {
     pub size: u32,
     pub msgs: Vec<Msg>,
     pub size_unknown: bool,
-    pub msgs_unknown: bool
+    pub msgs_unknown: bool,
+    // the built-in type this is, whose msgs the compiler provides
+    pub intrinsic: Option<Intrinsic>
 }
 
 impl DType {
@@ -17,129 +19,488 @@ impl DType {
             size,
             msgs,
             size_unknown,
-            msgs_unknown
+            msgs_unknown,
+            intrinsic: None
         }
     }
 
     pub fn from_literal(lit: Literal) -> Self {
         match lit {
-            Literal::String(_) => STRING,
+            Literal::String(_, _) => STRING,
             Literal::Char(_) => CHAR,
             Literal::Integer(_) => I32,
             Literal::Float(_) => F32,
             Literal::Byte(_) => B8,
+            Literal::B16(_) => B16,
+            Literal::B32(_) => B32,
+            Literal::B64(_) => B64,
+            Literal::Bool(_) => BOOL,
+            Literal::I8(_) => I8,
+            Literal::I16(_) => I16,
+            Literal::I64(_) => I64,
+            Literal::U8(_) => U8,
+            Literal::U16(_) => U16,
+            Literal::U32(_) => U32,
+            Literal::U64(_) => U64,
+            Literal::F64(_) => F64,
         }
     }
-    pub fn from_bytes(bytes: [u8; 6]) -> Self {
+    pub fn from_bytes(bytes: [u8; TYPE_BYTES]) -> Self {
         let mut size_slice = [0; 4];
-        for i in 0..4 {
-            size_slice[i] = bytes[i];
-        }
+        size_slice.copy_from_slice(&bytes[..4]);
         let size = u32::from_ne_bytes(size_slice);
         let size_unknown = bytes[4] == 1;
         let msgs_unknown = bytes[5] == 1;
-        Self { size, msgs: vec![], size_unknown, msgs_unknown }
+        let intrinsic = Intrinsic::from_byte(bytes[6]);
+        let mut table_slice = [0; 4];
+        table_slice.copy_from_slice(&bytes[7..]);
+        let msgs = msg_table(u32::from_ne_bytes(table_slice));
+        Self { size, msgs, size_unknown, msgs_unknown, intrinsic }
     }
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = self.size.to_ne_bytes().to_vec();
         bytes.push(self.size_unknown as u8);
         bytes.push(self.msgs_unknown as u8);
+        bytes.push(Intrinsic::to_byte(self.intrinsic));
+        bytes.extend_from_slice(&add_msg_table(&self.msgs).to_ne_bytes());
         bytes
     }
     pub fn to_expr(&self) -> Expr {
         let mut byte_lits = vec![];
-        for byte in self.to_bytes().clone() { byte_lits.push(Expr::Literal(Literal::Byte(byte))) }
-        Expr::Object(byte_lits)
+        for byte in self.to_bytes().clone() { byte_lits.push(Expr::Literal(Literal::Byte(byte), Span::default())) }
+        Expr::Object(byte_lits, Span::default())
     }
 
     pub fn get_msg(&self, msg_name: &String) -> Option<Msg> {
         for msg in &self.msgs {
             if &msg.name == msg_name { return Some(msg.clone()) }
         }
-        None
+        self.intrinsic.and_then(|intrinsic|
+            intrinsic_msgs(intrinsic).into_iter().find(|msg| &msg.name == msg_name)
+        )
     }
 
-    pub fn union(&self, other: &Self) -> Option<Self> {
-        if self != other { return None }
-        let size = self.size.max(other.size);
-        let msgs = if self.msgs.len() > other.msgs.len() { self.msgs.clone() }
-            else { other.msgs.clone() };
-        let size_unknown = self.size_unknown && other.size_unknown;
-        let msgs_unknown = self.msgs_unknown && other.msgs_unknown;
-        Some(Self::new(size, msgs, size_unknown, msgs_unknown))
+    // the type of a value that can be used as both self and other, if there is one. whatever either
+    // knows is known, so unknown sizes and msgs are only lower bounds to meet
+    pub fn unify(&self, other: &Self) -> Option<Self> {
+        let intrinsic = match (self.intrinsic, other.intrinsic) {
+            (Some(a), Some(b)) if a != b => return None,
+            (a, b) => a.or(b),
+        };
+        let (size, size_unknown) = match (self.size_unknown, other.size_unknown) {
+            (true, true) => (self.size.max(other.size), true),
+            (true, false) if other.size >= self.size => (other.size, false),
+            (false, true) if self.size >= other.size => (self.size, false),
+            (false, false) if self.size == other.size => (self.size, false),
+            _ => return None,
+        };
+        let (msgs, msgs_unknown) = match (self.msgs_unknown, other.msgs_unknown) {
+            (true, true) => {
+                let mut msgs = vec![];
+                for msg in &self.msgs {
+                    match other.msgs.iter().find(|found| found.name == msg.name) {
+                        Some(found) => msgs.push(msg.unify(found)?),
+                        None => msgs.push(msg.clone()),
+                    }
+                }
+                msgs.extend(other.msgs.iter().filter(|msg| self.msgs.iter().all(|known| known.name != msg.name)).cloned());
+                (msgs, true)
+            },
+            (true, false) => (Self::meet_msgs(other, self)?, false),
+            (false, true) => (Self::meet_msgs(self, other)?, false),
+            (false, false) if self.same_msgs(other) => (self.msgs.clone(), false),
+            _ => return None,
+        };
+        Some(Self { intrinsic, ..Self::new(size, msgs, size_unknown, msgs_unknown) })
+    }
+    // the msgs of known, if it has every msg needed
+    fn meet_msgs(known: &Self, needed: &Self) -> Option<Vec<Msg>> {
+        if !needed.msgs.iter().all(|msg| known.get_msg(&msg.name).is_some_and(|found| msg.accepts(&found))) { return None }
+        Some(known.msgs.clone())
     }
 
-    pub fn compose(&mut self, other: DType) {
-        self.size += other.size;
-        self.msgs.extend(other.msgs.into_iter());
+    // whether a value of other can be used as a value of self. a type with unknown size only knows
+    // how many bytes it needs at least, and one with unknown msgs only knows msgs it needs at least
+    pub fn is_assignable_from(&self, other: &Self) -> bool {
+        let size_fits = if self.size_unknown { other.size >= self.size }
+            else { !other.size_unknown && other.size == self.size };
+        if !size_fits { return false }
+
+        if self.msgs_unknown {
+            self.msgs.iter().all(|msg| other.get_msg(&msg.name).is_some_and(|found| msg.accepts(&found)))
+        } else if other.msgs_unknown {
+            // other is only bytes with some msgs, which self can give a type as long as they agree
+            other.msgs.iter().all(|msg| self.get_msg(&msg.name).is_some_and(|found| found.accepts(msg)))
+        } else {
+            self.same_msgs(other)
+        }
+    }
+    // exactly the same type
+    pub fn same_as(&self, other: &Self) -> bool {
+        self.size == other.size && self.size_unknown == other.size_unknown
+        && self.msgs_unknown == other.msgs_unknown && self.same_msgs(other)
+    }
+    fn same_msgs(&self, other: &Self) -> bool {
+        self.intrinsic == other.intrinsic && self.msgs.len() == other.msgs.len()
+        && self.msgs.iter().zip(other.msgs.iter()).all(|(a, b)| a.same_as(b))
     }
 }
 impl PartialEq for DType {
-    // used when a value of other is trying to be used as a value of self
+    // exactly the same type. whether a value can be used as another type is is_assignable_from
     fn eq(&self, other: &Self) -> bool {
-        if self.size_unknown {
-            if other.size < self.size {
-                return false
-            }
-        } else if other.size_unknown {
-            if self.size < other.size {
-                return false
-            }
-        } else {
-            if self.size != other.size {
-                return  false
-            }
+        self.same_as(other)
+    }
+}
+
+// how a type is named in diagnostics
+impl Display for DType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(intrinsic) = self.intrinsic {
+            if self.msgs.is_empty() { return write!(f, "{:?}", intrinsic) }
+        }
+        if let (Some(Intrinsic::Fn | Intrinsic::Closure), Some(call)) = (self.intrinsic, self.get_msg(&"!".to_string())) {
+            let params: Vec<String> = call.params.iter().map(|param| param.dtype.to_string()).collect();
+            let closure = if self.intrinsic == Some(Intrinsic::Closure) { "closure " } else { "" };
+            return write!(f, "{}({}) -> {}", closure, params.join(" "), call.ret_type)
         }
+        if self.size_unknown && self.msgs_unknown && self.size == 0 && self.msgs.is_empty() {
+            return write!(f, "an unknown type")
+        }
+        write!(f, "{}{} bytes", self.size, if self.size_unknown { "+" } else { "" })?;
+        let names: Vec<&str> = self.msgs.iter().map(|msg| msg.name.as_str()).collect();
+        match (names.is_empty(), self.msgs_unknown) {
+            (true, true) => Ok(()),
+            (true, false) => write!(f, " without msgs"),
+            (false, true) => write!(f, " with at least msgs {}", names.join(" ")),
+            (false, false) => write!(f, " with msgs {}", names.join(" ")),
+        }
+    }
+}
 
-        if self.msgs_unknown {
-            for msg in &self.msgs {
-                if other.get_msg(&msg.name) != Some(msg.clone()) {
-                    return false
-                }
-            }
-        } else if other.msgs_unknown {
-            for msg in &other.msgs {
-                if self.get_msg(&msg.name) == Some(msg.clone()) {
-                    return false
-                }
-            }
-        } else {
-            return self.msgs == other.msgs
+// the types built into the compiler. the prelude names them with `intrinsic: "I32"`
+thread_local! {
+    // the msgs of the types made at compile time. a type value's bytes only have room to say
+    // which table is its
+    static MSG_TABLES: RefCell<Vec<Vec<Msg>>> = const { RefCell::new(vec![]) };
+}
+// 0 is no msgs, so a type value needs no table unless it has some
+fn add_msg_table(msgs: &[Msg]) -> u32 {
+    if msgs.is_empty() { return 0 }
+    MSG_TABLES.with(|tables| {
+        let mut tables = tables.borrow_mut();
+        // the same msgs come back every time a type is turned into bytes
+        let same = |table: &Vec<Msg>| table.len() == msgs.len() && table.iter().zip(msgs).all(|(a, b)|
+            a.name == b.name && Rc::ptr_eq(&a.constructor, &b.constructor)
+        );
+        let index = match tables.iter().position(same) {
+            Some(index) => index,
+            None => {
+                tables.push(msgs.to_vec());
+                tables.len() - 1
+            },
+        };
+        index as u32 + 1
+    })
+}
+fn msg_table(id: u32) -> Vec<Msg> {
+    match id.checked_sub(1) {
+        Some(index) => MSG_TABLES.with(|tables| tables.borrow().get(index as usize).cloned().unwrap_or_default()),
+        None => vec![],
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Intrinsic {
+    Type, Decl, Fn, Closure,
+    B8, B16, B32, B64, Bool, Char, String,
+    I8, I16, I32, I64,
+    U8, U16, U32, U64,
+    F32, F64,
+}
+impl Intrinsic {
+    const ALL: [Intrinsic; 21] = [
+        Intrinsic::Type, Intrinsic::Decl, Intrinsic::Fn, Intrinsic::Closure,
+        Intrinsic::B8, Intrinsic::B16, Intrinsic::B32, Intrinsic::B64, Intrinsic::Bool, Intrinsic::Char, Intrinsic::String,
+        Intrinsic::I8, Intrinsic::I16, Intrinsic::I32, Intrinsic::I64,
+        Intrinsic::U8, Intrinsic::U16, Intrinsic::U32, Intrinsic::U64,
+        Intrinsic::F32, Intrinsic::F64,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|intrinsic| format!("{:?}", intrinsic) == name)
+    }
+    // 0 is a type that isn't intrinsic
+    fn from_byte(byte: u8) -> Option<Self> {
+        Self::ALL.get((byte as usize).checked_sub(1)?).copied()
+    }
+    fn to_byte(intrinsic: Option<Self>) -> u8 {
+        intrinsic.map_or(0, |intrinsic| intrinsic as u8 + 1)
+    }
+
+    pub fn dtype(self) -> DType {
+        match self {
+            Intrinsic::Type => TYPE,
+            Intrinsic::Decl => DECL,
+            Intrinsic::Fn => FN,
+            Intrinsic::Closure => CLOSURE,
+            Intrinsic::B8 => B8,
+            Intrinsic::B16 => B16,
+            Intrinsic::B32 => B32,
+            Intrinsic::B64 => B64,
+            Intrinsic::Bool => BOOL,
+            Intrinsic::Char => CHAR,
+            Intrinsic::String => STRING,
+            Intrinsic::I8 => I8,
+            Intrinsic::I16 => I16,
+            Intrinsic::I32 => I32,
+            Intrinsic::I64 => I64,
+            Intrinsic::U8 => U8,
+            Intrinsic::U16 => U16,
+            Intrinsic::U32 => U32,
+            Intrinsic::U64 => U64,
+            Intrinsic::F32 => F32,
+            Intrinsic::F64 => F64,
         }
-        true
     }
 }
 
 #[derive(Clone)]
 pub struct Msg {
     pub name: String,
-    constructor: Rc<dyn Fn(Option<Box<Expr>>, &Environment, Option<Box<Expr>>) -> Expr>,
+    constructor: Constructor,
     pub ret_type: DType,
-    pub arg_type: Option<DType>
+    // in the order arguments are passed
+    pub params: Vec<Param>,
+    fold: Option<Fold>
 }
+// builds the expr a msg is emitted as, from self and the args
+pub type Constructor = Rc<dyn Fn(Option<Box<Expr>>, &Environment, Vec<Expr>) -> Expr>;
+// evaluates a msg from the bytes of self and its args, for when they're all known at compile time
+pub type Fold = Rc<dyn Fn(&[u8], &[Vec<u8>]) -> Option<Literal>>;
 impl Msg {
     pub fn new(name: String,
-        constructor: Rc<dyn Fn(Option<Box<Expr>>, &Environment, Option<Box<Expr>>) -> Expr>,
+        constructor: Constructor,
         ret_type: DType,
-        arg_type: Option<DType>
+        params: Vec<Param>
     ) -> Self {
-        Self { name, constructor, ret_type, arg_type }
+        Self { name, constructor, ret_type, params, fold: None }
+    }
+    pub fn with_fold(mut self, fold: Fold) -> Self {
+        self.fold = Some(fold);
+        self
+    }
+
+    // parameter names are only documentation, so just their types have to match
+    pub fn same_as(&self, other: &Self) -> bool {
+        self.name == other.name && self.ret_type.same_as(&other.ret_type)
+        && self.params.len() == other.params.len()
+        && self.params.iter().zip(other.params.iter()).all(|(a, b)| a.dtype.same_as(&b.dtype))
+    }
+    // whether other can be emitted where self is expected: it takes at least the args self is
+    // given, and returns something self's return type can be used as
+    pub fn accepts(&self, other: &Self) -> bool {
+        self.name == other.name && self.ret_type.is_assignable_from(&other.ret_type)
+        && self.params.len() == other.params.len()
+        && self.params.iter().zip(other.params.iter()).all(|(a, b)| b.dtype.is_assignable_from(&a.dtype))
     }
 
-    pub fn construct(&self, self_expr: Option<Box<Expr>>, env: &Environment, arg: Option<Box<Expr>>) -> Expr {
-        (self.constructor) (self_expr, env, arg)
+    // a msg both can stand for, which returns what both return and takes what either takes. the
+    // constructor is self's
+    fn unify(&self, other: &Self) -> Option<Self> {
+        if self.name != other.name || self.params.len() != other.params.len() { return None }
+        let ret_type = self.ret_type.unify(&other.ret_type)?;
+        let params = self.params.iter().zip(other.params.iter())
+            .map(|(a, b)| {
+                let dtype = if a.dtype.is_assignable_from(&b.dtype) { a.dtype.clone() }
+                    else if b.dtype.is_assignable_from(&a.dtype) { b.dtype.clone() }
+                    else { return None };
+                Some(Param { dtype, ..a.clone() })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self { ret_type, params, ..self.clone() })
+    }
+
+    pub fn construct(&self, self_expr: Option<Box<Expr>>, env: &Environment, args: Vec<Expr>) -> Expr {
+        (self.constructor) (self_expr, env, args)
+    }
+    // the msg's value, if it can fold and self and args have static values
+    pub fn fold(&self, self_opt: &mut Option<Box<Expr>>, args: &mut [Expr], env: &mut Environment) -> Option<Literal> {
+        let fold = self.fold.as_ref()?;
+        let self_bytes = match self_opt {
+            Some(inner) => inner.interpret(env)?.0,
+            None => vec![],
+        };
+        let arg_bytes = args.iter_mut().map(|arg| arg.interpret(env).map(|(bytes, _)| bytes))
+            .collect::<Option<Vec<_>>>()?;
+        fold(&self_bytes, &arg_bytes)
     }
 }
 
 impl PartialEq for Msg {
     fn eq(&self, other: &Self) -> bool {
-        self.name == other.name && self.ret_type == other.ret_type
+        self.same_as(other)
     }
 }
 impl Debug for Msg {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct(format!("Msg {}", self.name).as_str())
-         .field("type", &self.ret_type)
-         .finish()
+        let mut debug = f.debug_struct(format!("Msg {}", self.name).as_str());
+        debug.field("type", &self.ret_type);
+        if !self.params.is_empty() { debug.field("params", &self.params); }
+        debug.finish()
     }
-}
\ No newline at end of file
+}
+
+#[derive(Clone, Debug)]
+pub struct Param {
+    pub name: String,
+    pub dtype: DType,
+    pub code: Code
+}
+impl Param {
+    pub fn new(name: String, dtype: DType) -> Self {
+        Self { name, dtype, code: Code::Value }
+    }
+    pub fn with_code(mut self, code: Code) -> Self {
+        self.code = code;
+        self
+    }
+}
+// what a msg does with an arg
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Code {
+    Value,
+    // runs it where the constructed expr puts it, calling it if it's a fn
+    Run,
+    // runs it as the body of a loop, which it can break out of
+    Loop,
+}
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use crate::{expr::Expr, token::span::Span};
+    use super::{DType, Msg, Param, super::{Environment, core_lib::*}};
+
+    fn msg(name: &str, ret_type: DType, params: Vec<DType>) -> Msg {
+        let constructor = |_: Option<Box<Expr>>, _: &Environment, _: Vec<Expr>| Expr::Object(vec![], Span::default());
+        let params = params.into_iter().map(|dtype| Param::new("p".into(), dtype)).collect();
+        Msg::new(name.into(), Rc::new(constructor), ret_type, params)
+    }
+    // a type that is only known to have at least these msgs
+    fn needs(size: u32, size_unknown: bool, msgs: Vec<Msg>) -> DType {
+        DType::new(size, msgs, size_unknown, true)
+    }
+
+    #[test]
+    fn assignability_lattice() {
+        let any = needs(0, true, vec![]);
+        let point = DType::new(12, vec![msg("x", I32.clone(), vec![]), msg("y", I64.clone(), vec![])], false, false);
+        let bytes_12 = needs(12, false, vec![]);
+        let has_x = needs(0, true, vec![msg("x", I32.clone(), vec![])]);
+        let has_x_i64 = needs(0, true, vec![msg("x", I64.clone(), vec![])]);
+        let takes_i32 = needs(0, true, vec![msg("f", VOID.clone(), vec![I32.clone()])]);
+        let takes_i64 = needs(0, true, vec![msg("f", VOID.clone(), vec![I64.clone()])]);
+        let gives_takes_i32 = DType::new(0, vec![msg("f", VOID.clone(), vec![I32.clone()])], false, false);
+        let gives_any = DType::new(0, vec![msg("f", VOID.clone(), vec![any.clone()])], false, false);
+
+        // (target, value, whether the value can be used as the target)
+        let table = [
+            (&I32, &I32, true),
+            (&I32, &I64, false),
+            (&I32, &U32, false),
+            (&I64, &I32, false),
+            (&any, &I32, true),
+            (&any, &point, true),
+            (&any, &any, true),
+            (&I32, &any, false),
+            (&needs(4, true, vec![]), &I64, true),
+            (&needs(4, true, vec![]), &I8, false),
+            (&I32, &needs(4, true, vec![]), false),
+            (&has_x, &point, true),
+            (&has_x, &I32, false),
+            (&has_x_i64, &point, false),
+            (&point, &has_x, false),
+            (&point, &bytes_12, true),
+            (&point, &needs(12, false, vec![msg("y", I64.clone(), vec![])]), true),
+            (&point, &needs(12, false, vec![msg("y", I32.clone(), vec![])]), false),
+            (&point, &needs(12, false, vec![msg("z", I32.clone(), vec![])]), false),
+            (&point, &needs(8, false, vec![]), false),
+            (&bytes_12, &point, true),
+            (&TYPE, &needs(TYPE.size, false, vec![]), true),
+            (&DECL, &needs(TYPE.size, false, vec![]), false),
+            (&takes_i32, &gives_takes_i32, true),
+            (&takes_i64, &gives_takes_i32, false),
+            (&takes_i32, &gives_any, true),
+            (&FN, &CLOSURE, false),
+            (&CLOSURE, &FN, false),
+            (&needs(8, true, vec![]), &CLOSURE, true),
+        ];
+        for (i, (target, value, expected)) in table.iter().enumerate() {
+            assert_eq!(target.is_assignable_from(value), *expected, "row {}: {:?} from {:?}", i, target, value);
+        }
+    }
+
+    #[test]
+    fn unification() {
+        let any = needs(0, true, vec![]);
+        let point = DType::new(12, vec![msg("x", I32.clone(), vec![]), msg("y", I64.clone(), vec![])], false, false);
+        let has_x = needs(0, true, vec![msg("x", I32.clone(), vec![])]);
+        let has_y = needs(0, true, vec![msg("y", I64.clone(), vec![])]);
+        let has_x_i64 = needs(0, true, vec![msg("x", I64.clone(), vec![])]);
+        let takes_any = needs(0, true, vec![msg("f", VOID.clone(), vec![any.clone()])]);
+        let takes_4 = needs(0, true, vec![msg("f", VOID.clone(), vec![needs(4, true, vec![])])]);
+
+        // (a, b, what a value that is both would be)
+        let table = [
+            (&any, &any, Some(any.clone())),
+            (&any, &I32, Some(I32.clone())),
+            (&I32, &I32, Some(I32.clone())),
+            (&I32, &I64, None),
+            (&I32, &U32, None),
+            (&needs(4, true, vec![]), &needs(8, true, vec![]), Some(needs(8, true, vec![]))),
+            (&needs(4, true, vec![]), &I64, Some(I64.clone())),
+            (&needs(4, true, vec![]), &I8, None),
+            (&needs(12, false, vec![]), &point, Some(point.clone())),
+            (&needs(8, false, vec![]), &point, None),
+            (&has_x, &point, Some(point.clone())),
+            (&has_x_i64, &point, None),
+            (&has_x, &has_y, Some(needs(0, true, vec![msg("x", I32.clone(), vec![]), msg("y", I64.clone(), vec![])]))),
+            (&has_x, &has_x_i64, None),
+            (&takes_any, &takes_4, Some(takes_any.clone())),
+            (&point, &TYPE, None),
+        ];
+        for (i, (a, b, expected)) in table.iter().enumerate() {
+            for (left, right) in [(a, b), (b, a)] {
+                let unified = left.unify(right);
+                assert_eq!(unified.is_some(), expected.is_some(), "row {}: {} and {}", i, left, right);
+                if let (Some(unified), Some(expected)) = (&unified, expected) {
+                    // in either order, though the msgs needed may be listed differently
+                    assert!(unified.is_assignable_from(expected) && expected.is_assignable_from(unified),
+                        "row {}: {} and {} gave {}", i, left, right, unified);
+                    // a value of the unified type can be used as either
+                    assert!(left.is_assignable_from(unified) && right.is_assignable_from(unified), "row {}", i);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn exact_equality() {
+        let point = DType::new(12, vec![msg("x", I32.clone(), vec![]), msg("y", I64.clone(), vec![])], false, false);
+        let types = [
+            I32.clone(), I64.clone(), U32.clone(), F32.clone(), TYPE.clone(), point.clone(),
+            DType::new(12, vec![msg("x", I32.clone(), vec![])], false, false),
+            DType::new(12, vec![msg("x", I32.clone(), vec![]), msg("y", I32.clone(), vec![])], false, false),
+            DType::new(12, vec![msg("f", VOID.clone(), vec![I32.clone()])], false, false),
+            DType::new(12, vec![msg("f", VOID.clone(), vec![I64.clone()])], false, false),
+            DType::new(12, vec![], false, true), DType::new(12, vec![], true, false), DType::new(0, vec![], true, true),
+        ];
+        for (i, a) in types.iter().enumerate() {
+            assert!(a.is_assignable_from(a), "not assignable from itself: {:?}", a);
+            for (j, b) in types.iter().enumerate() {
+                assert_eq!(a.same_as(b), i == j, "{:?} same as {:?}", a, b);
+                assert_eq!(a.same_as(b), b.same_as(a));
+                assert_eq!(a == b, a.same_as(b));
+            }
+        }
+    }
+}
@@ -1,20 +1,79 @@
-use super::{DType, dtype::Msg, core_lib};
+use std::collections::HashMap;
+use crate::token::span::Span;
+use super::{DType, TypeError, dtype::Msg, core_lib, fields, infer::TypeVar, intrinsics};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Environment {
     stack: Vec<Vec<u8>>,
     sp: usize,
     rt_stack_type: DType,
-    ct_stack_type: DType
+    ct_stack_type: DType,
+    // types of the modules imported by the module being checked, by path as written
+    imports: HashMap<String, DType>,
+    // whether runtime declarations are locals in a fn's stack frame
+    in_fn: bool,
+    // asm leaving the address of each runtime value a fn can name in jreg8#a, for capturing it by
+    // reference
+    rt_addrs: HashMap<String, String>,
+    // the module being checked, by where it is among the loaded modules. the prelude isn't one
+    module: Option<usize>,
+    // what the module around a fn declared before it, as (runtime, compile time) stack types
+    module_scope: Option<(DType, DType)>,
+    // the msgs of the module's runtime declarations, which only give a fn's label, so a fn can
+    // have them without capturing anything
+    module_decls: Vec<Msg>,
+    // how many loops what's being checked is in, which a fn's body is in none of
+    loops: u32,
+    // the types of declared values, as far as their uses so far say
+    type_vars: HashMap<String, TypeVar>
 }
 
 impl Environment {
+    // an environment with everything the prelude exports, and the control structures
     pub fn new() -> Self {
+        let (mut rt_stack_type, ct_stack_type) = core_lib::export();
+        rt_stack_type.msgs.extend(intrinsics::control_msgs());
+        Self { rt_stack_type, ct_stack_type, ..Self::empty() }
+    }
+    // an environment for checking module, the index it's loaded at
+    pub fn for_module(module: usize) -> Self {
+        Self { module: Some(module), ..Self::new() }
+    }
+    // an environment for checking the body of a fn in outer, which sees what the module declared
+    // but none of outer's locals
+    pub fn for_fn(outer: &Environment) -> Self {
+        let (rt_stack_type, ct_stack_type) = outer.module_scope.clone()
+            .unwrap_or_else(|| (DType { size: 0, ..outer.get_rt_stack_type() }, outer.get_ct_stack_type()));
+        Self {
+            stack: outer.stack.clone(),
+            sp: outer.sp,
+            rt_stack_type: rt_stack_type.clone(),
+            ct_stack_type: ct_stack_type.clone(),
+            imports: outer.imports.clone(),
+            in_fn: true,
+            rt_addrs: HashMap::new(),
+            module: outer.module,
+            module_scope: Some((rt_stack_type, ct_stack_type)),
+            module_decls: outer.module_decls.clone(),
+            loops: 0,
+            type_vars: HashMap::new()
+        }
+    }
+    // an environment with only `intrinsic:`, for checking the prelude
+    pub fn empty() -> Self {
         Self {
             stack: Vec::with_capacity(0),
             sp: 0,
-            rt_stack_type: core_lib::export(),
-            ct_stack_type: core_lib::export()
+            rt_stack_type: DType::new(0, vec![intrinsics::intrinsic_msg()], false, false),
+            ct_stack_type: DType::new(0, vec![intrinsics::intrinsic_msg()], false, false),
+            imports: HashMap::new(),
+            in_fn: false,
+            rt_addrs: HashMap::new(),
+            module: None,
+            module_scope: None,
+            module_decls: vec![],
+            loops: 0,
+            type_vars: HashMap::new()
         }
     }
 
@@ -38,6 +97,74 @@ impl Environment {
         self.rt_stack_type.size += size;
     }
 
+    // the first declaration of a name is the one it refers to
+    pub fn add_type_var(&mut self, type_var: TypeVar) {
+        self.type_vars.entry(type_var.name().to_string()).or_insert(type_var);
+    }
+    // a use of name needs a value of dtype, which narrows what its type is inferred to be, if it's
+    // a declared value. gives what it's inferred to be now. the value was already stored by the
+    // type it was declared with, so a use can't change how it's laid out
+    pub fn constrain(&mut self, name: &str, use_name: &str, dtype: &DType, span: Span) -> Result<Option<DType>, TypeError> {
+        let mut type_var = match self.type_vars.get(name) {
+            Some(type_var) => type_var.clone(),
+            None => return Ok(None),
+        };
+        type_var.constrain(use_name, dtype, span)?;
+        let inferred = type_var.resolve();
+        if let Some(msg) = self.rt_stack_type.msgs.iter_mut().find(|msg| msg.name == name) {
+            let stored = &msg.ret_type;
+            if stored.size != inferred.size || fields::in_register(stored) != fields::in_register(&inferred) {
+                return Err(TypeError::at(format!(
+                    "type of {} cannot be inferred: its value is already stored as {}, {} is {}", name, stored, use_name, dtype
+                ), span))
+            }
+            msg.ret_type = inferred.clone();
+        }
+        self.type_vars.insert(name.into(), type_var);
+        Ok(Some(inferred))
+    }
+
+    pub fn in_fn(&self) -> bool {
+        self.in_fn
+    }
+
+    // checks code that's run as the body of a loop
+    pub fn in_loop<T>(&mut self, check: impl FnOnce(&mut Self) -> T) -> T {
+        self.loops += 1;
+        let checked = check(self);
+        self.loops -= 1;
+        checked
+    }
+    pub fn can_break(&self) -> bool {
+        self.loops > 0
+    }
+
+    pub fn module(&self) -> Option<usize> {
+        self.module
+    }
+    pub fn add_module_decl(&mut self, msg: Msg) {
+        self.module_decls.push(msg);
+    }
+    // the module's runtime declaration name refers to, unless a local of the fn shadows it
+    pub fn get_module_decl(&self, name: &str) -> Option<Msg> {
+        if self.rt_addrs.contains_key(name) { return None }
+        self.module_decls.iter().rev().find(|msg| msg.name == name).cloned()
+    }
+
+    pub fn add_rt_addr(&mut self, name: String, addr: String) {
+        self.rt_addrs.insert(name, addr);
+    }
+    pub fn get_rt_addr(&self, name: &str) -> Option<String> {
+        self.rt_addrs.get(name).cloned()
+    }
+
+    pub fn add_import(&mut self, path: String, dtype: DType) {
+        self.imports.insert(path, dtype);
+    }
+    pub fn get_import(&self, path: &str) -> Option<DType> {
+        self.imports.get(path).cloned()
+    }
+
     pub fn push(&mut self, bytes: Vec<u8>) -> usize {
         self.stack.push(bytes);
         let tmp = self.sp;
@@ -47,4 +174,26 @@ impl Environment {
     pub fn get_stack(&self, addr: usize) -> Option<&Vec<u8>> {
         self.stack.get(addr)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use crate::token::span::Span;
+    use super::{DType, Environment, TypeVar, core_lib::I64, fields};
+
+    #[test]
+    fn constrained_layout() {
+        // a use can narrow a stored value's type, but not change the size its slot was given
+        let mut env = Environment::new();
+        let stored = DType::new(4, vec![], true, true);
+        env.add_type_var(TypeVar::new("x", &stored, Span::default()));
+        env.add_rt_msg(fields::local_msg("x".into(), stored.clone(), 4));
+        let err = env.constrain("x", "argument value of f", &I64, Span::default()).unwrap_err().to_string();
+        assert!(err.contains("type of x cannot be inferred: its value is already stored as"), "{}", err);
+        assert!(env.get_rt_stack_type().get_msg(&"x".to_string()).unwrap().ret_type.same_as(&stored));
+
+        let narrowed = DType::new(4, vec![], false, true);
+        let inferred = env.constrain("x", "argument value of g", &narrowed, Span::default()).unwrap().unwrap();
+        assert!(inferred.same_as(&narrowed));
+        assert!(env.get_rt_stack_type().get_msg(&"x".to_string()).unwrap().ret_type.same_as(&narrowed));
+    }
+}
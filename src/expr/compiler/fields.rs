@@ -0,0 +1,221 @@
+use std::rc::Rc;
+use crate::expr::Expr;
+use super::{DType, Environment, TypeCheck, core_lib::*, dtype::{Intrinsic, Msg, Param}, intrinsics::asm_node};
+
+// a struct's fields are laid out in order from its address, which is what a struct value is at
+// runtime. the locals of a fn are laid out the same way, down from rbp
+
+// params and args are laid out by the jovis calling convention, which is described in README.md
+
+// values that fit in a register are passed in one, anything else by its address
+pub fn in_register(dtype: &DType) -> bool {
+    dtype.intrinsic.is_some() && matches!(dtype.size, 1 | 2 | 4 | 8)
+}
+
+// the getter and `set_` msgs of a field at offset in a struct. see holds_closure for why some have
+// no setter
+pub fn field_msgs(name: String, dtype: DType, offset: u32) -> Vec<Msg> {
+    let get_type = dtype.clone();
+    let getter = move |self_expr: Option<Box<Expr>>, _: &Environment, _: Vec<Expr>| asm_node(&get_type, format!(
+        " mov jreg8#p, j# {}\n{}", self_expr.unwrap().to_syntax(), load(&get_type, &at("jreg8#p", offset as i64))
+    ));
+    let set_type = dtype.clone();
+    // the value is pushed so computing it can't clobber the struct's address
+    let setter = move |self_expr: Option<Box<Expr>>, _: &Environment, args: Vec<Expr>| asm_node(&VOID, format!(
+        " push j# {}\n push j# {}\n pop jreg8#v\n pop jreg8#p\n{}",
+        self_expr.unwrap().to_syntax(), args[0].to_syntax(), store(&set_type, "jreg8#p", offset as i64)
+    ));
+    let mut msgs = vec![Msg::new(name.clone(), Rc::new(getter), dtype.clone(), vec![])];
+    if !holds_closure(&dtype) {
+        msgs.push(Msg::new(format!("set_{}", name), Rc::new(setter), VOID, vec![Param::new("value".into(), dtype)]));
+    }
+    msgs
+}
+// a msg of a struct embedded at offset in another, done on the embedded struct
+pub fn shifted(msg: Msg, offset: u32) -> Msg {
+    let inner = msg.clone();
+    let constructor = move |self_expr: Option<Box<Expr>>, env: &Environment, args: Vec<Expr>| {
+        let embedded = asm_node(&B64, format!(
+            " mov jreg8#p, j# {}\n add jreg8#p, {}\n jret# addr( jreg8#p )\n", self_expr.unwrap().to_syntax(), offset
+        ));
+        inner.construct(Some(Box::new(embedded)), env, args)
+    };
+    Msg::new(msg.name, Rc::new(constructor), msg.ret_type, msg.params)
+}
+
+// the getter of a local whose value ends offset bytes below rbp
+pub fn local_msg(name: String, dtype: DType, offset: u32) -> Msg {
+    let get_type = dtype.clone();
+    let getter = move |_: Option<Box<Expr>>, _: &Environment, _: Vec<Expr>|
+        asm_node(&get_type, load(&get_type, &at("rbp", -(offset as i64))));
+    Msg::new(name, Rc::new(getter), dtype, vec![])
+}
+// initializes that local with val
+pub fn local_store(dtype: &DType, offset: u32, val: &Expr) -> Expr {
+    asm_node(&VOID, format!(" mov jreg8#v, j# {}\n{}", val.to_syntax(), store(dtype, "rbp", -(offset as i64))))
+}
+// makes room for a fn's locals, so what it pushes goes under them
+pub fn reserve(size: u32) -> Expr {
+    asm_node(&VOID, format!(" sub rsp, {}\n", size))
+}
+
+// leave the address of a local or param in jreg8#a
+pub fn local_addr(offset: u32) -> String {
+    format!(" lea jreg8#a, [{}]\n", at("rbp", -(offset as i64)))
+}
+pub fn param_addr(offset: u32) -> String {
+    format!(" lea jreg8#a, [{}]\n", at("rbp", 16 + offset as i64))
+}
+
+// the space a value takes as an arg
+pub fn slot_size(dtype: &DType) -> u32 {
+    dtype.size.div_ceil(8) * 8
+}
+// the getter of a param whose slot is offset bytes into the args
+pub fn param_msg(name: String, dtype: DType, offset: u32) -> Msg {
+    let get_type = dtype.clone();
+    let getter = move |_: Option<Box<Expr>>, _: &Environment, _: Vec<Expr>|
+        asm_node(&get_type, load(&get_type, &at("rbp", 16 + offset as i64)));
+    Msg::new(name, Rc::new(getter), dtype, vec![])
+}
+// puts val in its slot, offset bytes into the args at base
+pub fn store_arg(dtype: &DType, base: &str, offset: u32, val: &Expr) -> String {
+    format!(" mov jreg8#v, j# {}\n{}", val.to_syntax(), store(dtype, base, offset as i64))
+}
+
+// a runtime value a closure captures. by value it's copied into the closure's record, by reference
+// the asm leaving its address in jreg8#a gives what goes there
+pub enum Captured {
+    Value(Expr),
+    Ref(String),
+}
+impl Captured {
+    // the space it takes in the record
+    pub fn size(&self, dtype: &DType) -> u32 {
+        match self {
+            Captured::Value(_) => dtype.size,
+            Captured::Ref(_) => 8,
+        }
+    }
+    // copies it into its slot in the record, which ends record bytes below rbp
+    pub fn store(&self, dtype: &DType, record: u32, slot: u32) -> String {
+        let offset = slot as i64 - record as i64;
+        match self {
+            Captured::Value(val) => format!(" mov jreg8#v, j# {}\n{}", val.to_syntax(), store(dtype, "rbp", offset)),
+            Captured::Ref(addr) => format!("{} mov qword [{}], jreg8#a\n", addr, at("rbp", offset)),
+        }
+    }
+    // the getter the closure has for it, and the asm leaving its address. the record's address is
+    // the arg after the params, which take params_size
+    pub fn msg(&self, name: String, dtype: DType, params_size: u32, slot: u32) -> (Msg, String) {
+        let record = format!(" mov jreg8#a, qword [rbp+{}]\n", 16 + params_size);
+        let addr = match self {
+            Captured::Value(_) => format!("{} add jreg8#a, {}\n", record, slot),
+            Captured::Ref(_) => format!("{} mov jreg8#a, qword [jreg8#a+{}]\n", record, slot),
+        };
+        let get_type = dtype.clone();
+        let get_addr = addr.clone();
+        let getter = move |_: Option<Box<Expr>>, _: &Environment, _: Vec<Expr>|
+            asm_node(&get_type, format!("{}{}", get_addr, load(&get_type, "jreg8#a")));
+        (Msg::new(name, Rc::new(getter), dtype, vec![]), addr)
+    }
+}
+// stores what a closure captured in its record, then the closure in the local value bytes below
+// rbp: the fn's address then the record's
+pub fn closure_node(dtype: &DType, record: u32, value: u32, stores: String, code: &Expr) -> Expr {
+    asm_node(dtype, format!(
+        "{} lea jreg8#r, [{}]\n mov qword [{}], jreg8#r\n mov jreg8#r, j# {}\n mov qword [{}], jreg8#r\n lea jreg8#r, [{}]\n jret# addr( jreg8#r )\n",
+        stores, at("rbp", -(record as i64)), at("rbp", 8 - value as i64), code.to_syntax(),
+        at("rbp", -(value as i64)), at("rbp", -(value as i64))
+    ))
+}
+// whether a value has a closure in it, directly or in a field. such a value can't be returned or
+// set in a field: the closure's record is in the frame of the fn that made it, so it would
+// outlive the record, and one made in a loop would be overwritten by the next
+pub fn holds_closure(dtype: &DType) -> bool {
+    dtype.intrinsic == Some(Intrinsic::Closure) || dtype.intrinsic.is_none()
+        && dtype.msgs.iter().any(|msg| msg.params.is_empty() && holds_closure(&msg.ret_type))
+}
+
+// loads the value at addr, or gives addr itself if the value doesn't fit in a register
+fn load(dtype: &DType, addr: &str) -> String {
+    if in_register(dtype) {
+        format!(" mov jreg{}#v, {} [{}]\n jret# val( jreg8#v )\n", dtype.size, size_name(dtype.size), addr)
+    } else {
+        format!(" lea jreg8#v, [{}]\n jret# addr( jreg8#v )\n", addr)
+    }
+}
+// stores what's in jreg8#v at base+offset, copying it from the address in jreg8#v if it doesn't fit
+// in a register
+fn store(dtype: &DType, base: &str, offset: i64) -> String {
+    if in_register(dtype) {
+        return format!(" mov {} [{}], jreg{}#v\n", size_name(dtype.size), at(base, offset), dtype.size)
+    }
+    let mut text = String::new();
+    let mut copied = 0;
+    while copied < dtype.size {
+        let chunk = [8, 4, 2, 1].iter().copied().find(|chunk| dtype.size - copied >= *chunk).unwrap();
+        text.push_str(&format!(" mov jreg{}#t, {} [jreg8#v+{}]\n mov {} [{}], jreg{}#t\n",
+            chunk, size_name(chunk), copied, size_name(chunk), at(base, offset + copied as i64), chunk
+        ));
+        copied += chunk;
+    }
+    text
+}
+fn at(base: &str, offset: i64) -> String {
+    if offset < 0 { format!("{}-{}", base, -offset) } else { format!("{}+{}", base, offset) }
+}
+fn size_name(size: u32) -> &'static str {
+    match size {
+        1 => "byte",
+        2 => "word",
+        4 => "dword",
+        _ => "qword",
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+    use crate::expr::test_support::parse;
+    use super::{DType, Environment, TypeCheck, I64, field_msgs, super::{interpreter::Interpret, intrinsics::closure_type}};
+
+    #[test]
+    fn struct_fields() {
+        // a type value keeps its msgs, and an embedded struct's work where it is in the new one
+        let mut env = Environment::new();
+        parse("Point ;= (x; I32 y; I64)").check(&mut env).unwrap();
+        let (bytes, _) = parse("(tag; B8 Point)").interpret(&mut env).unwrap();
+        let tagged = DType::from_bytes(bytes.try_into().unwrap());
+        let names: Vec<&str> = tagged.msgs.iter().map(|msg| msg.name.as_str()).collect();
+        assert_eq!(tagged.size, 13);
+        assert_eq!(names, ["tag", "set_tag", "x", "set_x", "y", "set_y"]);
+
+        // fields are loaded and stored at their offsets from the struct's address
+        let mut fields = parse("{Point ;= (x; I32 y; I64)\n Tagged ;= (tag; B8 Point)\n \
+            ||{ p; Point = [1 2i64]\n p.set_y: {p.y}\n t; Tagged = [1b p]\n t.set_x: 3 }}");
+        fields.check(&mut Environment::new()).unwrap();
+        let text = fields.to_syntax();
+        assert!(text.contains(" mov jreg8#v, qword [jreg8#p+4]"), "{}", text);
+        assert!(text.contains(" mov qword [jreg8#p+4], jreg8#v"), "{}", text);
+        assert!(text.contains(" add jreg8#p, 1"), "{}", text);
+        assert!(text.contains(" mov dword [jreg8#p+0], jreg4#v"), "{}", text);
+    }
+
+    #[test]
+    fn closures() {
+        // a closure is made in the frame, so one made in a loop doesn't grow the stack
+        let mut made = parse("||{ b ;= {asm [] I64 \" mov jreg8#a, 7\n jret# val( jreg8#a )\"}\n \
+            loop: { f ;= |&: b| { b + 1i64 }\n f.!\n break }\n 0 }");
+        made.check(&mut Environment::new()).unwrap();
+        let text = made.to_syntax();
+        assert!(text.contains(" jret# addr( jreg8#r )"), "{}", text);
+        assert!(!text.contains(" push jreg8#r"), "{}", text);
+
+        // and it can't be returned or set in a field
+        let returned = parse("||{ b ;= {asm [] I64 \" mov jreg8#a, 7\n jret# val( jreg8#a )\"}\n |&: b| { b } }")
+            .check(&mut Environment::new()).unwrap_err().to_string();
+        assert!(returned.contains("can't return a closure"), "{}", returned);
+        let names: Vec<String> = field_msgs("f".into(), closure_type(vec![], I64), 0).into_iter().map(|msg| msg.name).collect();
+        assert_eq!(names, ["f"]);
+    }
+}
@@ -0,0 +1,53 @@
+use crate::{expr::Expr, token::span::Span};
+use super::{DType, TypeError, core_lib::UNKNOWN};
+
+// the type of a decl, inferred from its uses. it starts as what the decl says, which may be
+// nothing, and each use unifies in what it needs
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeVar {
+    name: String,
+    dtype: DType,
+    // what each use was, its type and where it is, to name the ones that conflict
+    uses: Vec<(String, DType, Span)>,
+}
+impl TypeVar {
+    pub fn new(name: &str, declared: &DType, declared_at: Span) -> Self {
+        let uses = if declared.same_as(&UNKNOWN) { vec![] }
+            else { vec![("declared type".into(), declared.clone(), declared_at)] };
+        Self { name: name.into(), dtype: declared.clone(), uses }
+    }
+
+    pub fn constrain(&mut self, use_name: &str, dtype: &DType, span: Span) -> Result<(), TypeError> {
+        match self.dtype.unify(dtype) {
+            Some(unified) => {
+                self.dtype = unified;
+                self.uses.push((use_name.into(), dtype.clone(), span));
+                Ok(())
+            },
+            None => {
+                // the first use it can't be unified with, or all of them together if each is fine alone
+                let conflict = self.uses.iter().find(|(_, used, _)| used.unify(dtype).is_none())
+                    .map_or("earlier uses together".into(), |(used_name, used, at)| format!("{} at {} is {}", used_name, at, used));
+                Err(TypeError::at(format!(
+                    "type of {} cannot be inferred: {}, {} is {}", self.name, conflict, use_name, dtype
+                ), span))
+            },
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn resolve(&self) -> DType {
+        self.dtype.clone()
+    }
+}
+
+// where the type of a decl is written, or the decl itself if it has none. this has to be taken
+// before the decl is interpreted, which replaces the type with its bytes
+pub fn declared_at(decl: &Expr) -> Span {
+    match decl {
+        Expr::BinaryOpt(_, _, Some(dtype)) => dtype.span(),
+        _ => decl.span(),
+    }
+}
@@ -1,67 +1,74 @@
 use std::{rc::Rc, str::FromStr};
-use super::{Expr, Environment, DType, dtype::Msg, core_lib::*, TypeCheck, decl::Decl};
-use crate::{expr::compiler::fill_slice_with_vec, token::{Token, TokenType, literal::Literal}};
+use super::{Expr, Environment, DType, dtype::{Msg, Param}, core_lib::*, TypeCheck, decl::Decl, fields, intrinsics::fn_type};
+use crate::{expr::compiler::fill_slice_with_vec, token::{Token, TokenType, literal::Literal, span::Span}};
 
 pub trait Interpret {
     fn interpret(&mut self, env: &mut Environment) -> Option<(Vec<u8>, DType)>;
-    fn interpret_new_env(&mut self) -> Option<(Vec<u8>, DType)>;
 }
 
 impl Interpret for Expr {
     fn interpret(&mut self, env: &mut Environment) -> Option<(Vec<u8>, DType)> {
         match self {
             Expr::Binary(left, op, right) => if op.ttype == TokenType::Equal {
-                let (decl_bytes, decl_type) = match left.interpret(env) {
-                    Some(v) => v,
-                    None => return None,
-                };
-                if decl_type != DECL { return None }
+                let (decl_bytes, decl_type) = left.interpret(env)?;
+                if !DECL.is_assignable_from(&decl_type) { return None }
                 
-                let mut decl_slice = [0; 22]; // TODO: find more efficient way to do this
-                for i in 0..22 {
-                    decl_slice[i] = decl_bytes[i];
-                }
-                let decl = match Decl::from_bytes(decl_slice, env) {
-                    Some(v) => v,
-                    None => return None,
-                };
+                let mut decl_slice = [0; DECL_BYTES];
+                decl_slice.copy_from_slice(&decl_bytes[..DECL_BYTES]);
+                let decl = Decl::from_bytes(decl_slice, env)?;
                 
                 decl.ct_initialize(*right.clone(), env)
+            } else if op.ttype == TokenType::RightArrow {
+                // `(x; I32 I64) -> I32`, with a param for each part of the left type, or the left
+                // type as the only param if it isn't written out
+                let params = match left.as_mut() {
+                    Expr::Type(exprs, _) => exprs.iter_mut().map(|expr| {
+                        let dtype = expr.check(env).ok()?;
+                        if DECL.is_assignable_from(&dtype) {
+                            let decl = Decl::from_expr(expr, env)?;
+                            Some(Param::new(decl.name, decl.dtype))
+                        } else { Some(Param::new("_".into(), type_value(expr, env)?)) }
+                    }).collect::<Option<Vec<_>>>()?,
+                    _ => vec![Param::new("_".into(), type_value(left, env)?)],
+                };
+                let ret_type = type_value(right, env)?;
+                Some((fn_type(params, ret_type).to_bytes(), TYPE))
             } else { panic!("unexpected binary operator") },
-            Expr::MsgEmission(self_opt, msg_name, arg_opt) => {
+            Expr::MsgEmission(self_opt, msg_name, args) => {
+                let start = self_opt.as_ref().map_or(msg_name.span, |inner| inner.span());
+                let span = start.to(&args.last().map_or(msg_name.span, |arg| arg.span()));
                 let self_t = match self_opt {
                     Some(inner) => inner.interpret(env)?.1,
                     None => env.get_ct_stack_type(),
                 };
                 let msg = self_t.get_msg(&msg_name.lexeme)?;
-                // check if arg matched msg's arg type
-                if let Some(arg) = arg_opt {
-                    let arg_type = match &msg.arg_type {
-                        Some(arg_type) => arg_type,
-                        None => return None,
-                    };
-                    if &arg.interpret(env)?.1 != arg_type { return None }
-                } else {
-                    if let Some(_) = msg.arg_type {
-                        return None
-                    }
+                // check args against msg's params by position
+                if args.len() != msg.params.len() { return None }
+                for (arg, param) in args.iter_mut().zip(msg.params.iter()) {
+                    if !param.dtype.is_assignable_from(&arg.interpret(env)?.1) { return None }
                 }
 
-                let mut constructed_expr = msg.construct(self_opt.clone(), env, arg_opt.clone());
+                if let Some(lit) = msg.fold(self_opt, args, env) {
+                    let mut folded = Expr::Literal(lit, span);
+                    let bytes = folded.interpret(env)?.0;
+                    *self = folded;
+                    return Some((bytes, msg.ret_type))
+                }
+                let mut constructed_expr = msg.construct(self_opt.clone(), env, args.clone());
+                constructed_expr.stamp(span);
                 let (bytes, dtype) = constructed_expr.interpret(env)?;
-                if dtype != msg.ret_type { return None }
+                if !msg.ret_type.is_assignable_from(&dtype) { return None }
                 *self = constructed_expr;
-                Some((bytes, dtype))
+                Some((bytes, msg.ret_type))
             },
             Expr::BinaryOpt(left, op, right_opt) => {
                 match op.ttype {
                     TokenType::Semicolon => {
                         let name = match *left.clone() {
-                            Expr::MsgEmission(None, name, None) => name.lexeme,
+                            Expr::MsgEmission(None, name, args) if args.is_empty() => name.lexeme,
                             _ => panic!("expected identifier")
                         };
-                        let mut bytes = vec![];
-                        name.chars().for_each(|c|{ bytes.push(c as u8) });
+                        let bytes = name.into_bytes();
                         let str_size = bytes.len();
                         let addr = env.push(bytes);
                         let mut name = addr.to_ne_bytes().to_vec();
@@ -70,32 +77,30 @@ impl Interpret for Expr {
                         let type_opt = right_opt.as_mut().map(|right|
                             right.interpret(env).map(|(bytes, dtype)| {
                                 // check dtype
-                                if dtype != TYPE { return None }
+                                if !TYPE.is_assignable_from(&dtype) { return None }
                                 Some(bytes)
                             }).unwrap_or(None)
                         ).unwrap_or(None);
-                        let type_bytes = type_opt.unwrap_or(vec![0,0,0,0,1,1]);
+                        let type_bytes = type_opt.unwrap_or_else(|| UNKNOWN.to_bytes());
 
                         let mut decl_bytes = name;
-                        decl_bytes.extend(type_bytes.into_iter());
+                        decl_bytes.extend(type_bytes);
                         Some((decl_bytes, DECL))
                     },
                     _ => panic!("unexpected operator in binary_opt")
                 }
             },
-            Expr::Asm(_, _, text_expr) => { // TODO
+            Expr::Asm(_, _, text_expr, _) => { // TODO
                 let mut text = match text_expr.interpret(env) { // TODO: if string literal, get string directly
-                    Some((text_bytes, text_type)) => if text_type == STRING {
+                    Some((text_bytes, text_type)) if STRING.is_assignable_from(&text_type) => {
                         if text_bytes.len() as u32 == STRING.size {
-                            let mut text_slice = [0; 16]; // TODO: find more efficient way to do this
-                            for i in 0..16 {
-                                text_slice[i] = text_bytes[i];
-                            }
+                            let mut text_slice = [0; STRING_BYTES];
+                            text_slice.copy_from_slice(&text_bytes[..STRING_BYTES]);
                             str_from_jstr(text_slice, env).expect("could not get string from stack")
                         }
                         else { panic!("jstr is of incorrect size") }
-                    } else { return None },
-                    None => return None
+                    },
+                    _ => return None
                 };
                 // TODO
                 // for (i,_) in text.match_indices("j#") {
@@ -111,13 +116,8 @@ impl Interpret for Expr {
                 // TODO
                 let mut val = vec![];
                 for (i,_) in text.clone().match_indices("jret(") {
-                    let mut n = i+5;
-                    let mut operand = "".to_string();
-                    loop {
-                        let c = text.chars().nth(n)?;
-                        if c == ')' { break };
-                        operand.push(c); n+=1
-                    }
+                    let n = i + 5 + text[(i+5)..].find(')')?;
+                    let operand = text[(i+5)..n].to_string();
                     let addr = usize::from_str(operand.trim()).ok()?;
                     val = env.get_stack(addr)?.clone();
                     // remove return from text
@@ -126,7 +126,9 @@ impl Interpret for Expr {
                 // TODO: simulate running assembly
                 Some((val, VOID))
             },
-            Expr::Object(exprs) => {
+            // modules only exist at compile time
+            Expr::Import(path, _) => Some((vec![], env.get_import(path)?)),
+            Expr::Object(exprs, _) => {
                 let mut bytes = vec![];
                 let mut msgs = vec![];
                 let mut size = 0;
@@ -138,11 +140,11 @@ impl Interpret for Expr {
                             if let Some((mut val_bytes, dtype)) = val {
                                 bytes.append(&mut val_bytes);
                                 let byte_lits: Vec<Expr> = val_bytes.iter().map(|b|
-                                    Expr::Literal(Literal::Byte(*b))
+                                    Expr::Literal(Literal::Byte(*b), Span::default())
                                 ).collect();
-                                let constructor = move |_: Option<Box<Expr>>, _: &Environment, _: Option<Box<Expr>>|
-                                    { Expr::Object(byte_lits.clone()) };
-                                msgs.push(Msg::new(decl.name, Rc::new(constructor), dtype.clone(), None));
+                                let constructor = move |_: Option<Box<Expr>>, _: &Environment, _: Vec<Expr>|
+                                    { Expr::Object(byte_lits.clone(), Span::default()) };
+                                msgs.push(Msg::new(decl.name, Rc::new(constructor), dtype.clone(), vec![]));
                                 size += dtype.size;
                             } else { return None }
                         }
@@ -160,7 +162,7 @@ impl Interpret for Expr {
                 }
                 Some((bytes,  DType::new(size, msgs, false, true)))
             },
-            Expr::CodeBlock(exprs) => {
+            Expr::CodeBlock(exprs, _) => {
                 let mut last_bytes = vec![];
                 let mut last_type = DType::new(0, vec![], false, true);
                 for expr in exprs {
@@ -170,51 +172,50 @@ impl Interpret for Expr {
                 }
                 Some((last_bytes, last_type))
             },
-            Expr::Fn(_, _) => None, // this value is available at comptime but in the linker
+            Expr::Fn(_, _, _) => None, // this value is available at comptime but in the linker
             // TODO: make fn appear in the comptime stack
-            Expr::Type(exprs) => { // TODO: msg body
+            Expr::Type(exprs, _) => { // TODO: msg body
                 let mut type_val = VOID;
                 for expr in exprs {
                     let dtype = expr.check(env).ok()?;
-                    if dtype == TYPE {
-                        let (bytes,_) = match expr.interpret(env) {
-                            Some(v) => v,
-                            None => return None,
-                        };
+                    if TYPE.is_assignable_from(&dtype) {
+                        let (bytes,_) = expr.interpret(env)?;
                         let composing_type = if bytes.len() as u32 == TYPE.size {
-                            let mut type_slice = [0; 6];
+                            let mut type_slice = [0; TYPE_BYTES];
                             fill_slice_with_vec(&mut type_slice, bytes);
                             DType::from_bytes(type_slice)
                         } else { panic!("value of unexpected size") };
+                        // its msgs work on it where it is in the new type
+                        let offset = type_val.size;
+                        type_val.msgs.extend(composing_type.msgs.into_iter().map(|msg| fields::shifted(msg, offset)));
                         type_val.size += composing_type.size;
-                        type_val.msgs.extend(composing_type.msgs.into_iter());
                     }
-                    else if dtype == DECL {
-                        let mut decl_slice = [0; 22];
+                    else if DECL.is_assignable_from(&dtype) {
+                        let mut decl_slice = [0; DECL_BYTES];
                         fill_slice_with_vec(&mut decl_slice, expr.interpret(env)?.0);
                         let decl = Decl::from_bytes(decl_slice, env)?;
-                        let name = decl.name;
                         let composing_type = decl.dtype;
-                        type_val.size += composing_type.size;
-                        let constructor = move |_: Option<Box<Expr>>, _: &Environment, _: Option<Box<Expr>>|
-                        { Expr::Object(vec![]) }; // TODO: add asm node
-                        type_val.msgs.push(Msg::new(name, Rc::new(constructor), composing_type, None));
+                        let size = composing_type.size;
+                        type_val.msgs.extend(fields::field_msgs(decl.name, composing_type, type_val.size));
+                        type_val.size += size;
                     }
                     else { return None }
                 }
                 Some((type_val.to_bytes(), TYPE))
             },
-            Expr::Literal(inner) => match inner.clone() {
-                Literal::String(val) => {
-                    let mut bytes = vec![];
-                    val.chars().for_each(|c|{ bytes.push(c as u8) });
+            Expr::Literal(inner, _) => match inner.clone() {
+                Literal::String(bytes, _) => {
                     let str_size = bytes.len();
                     let addr = env.push(bytes);
                     let mut val = addr.to_ne_bytes().to_vec();
                     val.extend_from_slice(&str_size.to_ne_bytes());
                     Some((val, DType::from_literal(inner.clone())))
                 },
-                Literal::Char(c) => Some((vec![c as u8], DType::from_literal(inner.clone()))),
+                Literal::Char(c) => {
+                    let mut utf8 = [0; 4];
+                    c.encode_utf8(&mut utf8);
+                    Some((utf8.to_vec(), DType::from_literal(inner.clone())))
+                },
                 Literal::Integer(i) => {
                     let mut vec = vec![];
                     vec.extend_from_slice(&i.to_le_bytes());
@@ -226,11 +227,28 @@ impl Interpret for Expr {
                     Some((vec, DType::from_literal(inner.clone())))
                 },
                 Literal::Byte(b) => Some((vec![b], DType::from_literal(inner.clone()))),
+                Literal::B16(b) => Some((b.to_le_bytes().to_vec(), DType::from_literal(inner.clone()))),
+                Literal::B32(b) => Some((b.to_le_bytes().to_vec(), DType::from_literal(inner.clone()))),
+                Literal::B64(b) => Some((b.to_le_bytes().to_vec(), DType::from_literal(inner.clone()))),
+                Literal::Bool(b) => Some((vec![b as u8], DType::from_literal(inner.clone()))),
+                Literal::I8(i) => Some((i.to_le_bytes().to_vec(), DType::from_literal(inner.clone()))),
+                Literal::I16(i) => Some((i.to_le_bytes().to_vec(), DType::from_literal(inner.clone()))),
+                Literal::I64(i) => Some((i.to_le_bytes().to_vec(), DType::from_literal(inner.clone()))),
+                Literal::U8(u) => Some((u.to_le_bytes().to_vec(), DType::from_literal(inner.clone()))),
+                Literal::U16(u) => Some((u.to_le_bytes().to_vec(), DType::from_literal(inner.clone()))),
+                Literal::U32(u) => Some((u.to_le_bytes().to_vec(), DType::from_literal(inner.clone()))),
+                Literal::U64(u) => Some((u.to_le_bytes().to_vec(), DType::from_literal(inner.clone()))),
+                Literal::F64(f) => Some((f.to_le_bytes().to_vec(), DType::from_literal(inner.clone()))),
             },
         }
     }
+}
 
-    fn interpret_new_env(&mut self) -> Option<(Vec<u8>, DType)> {
-        self.interpret(&mut Environment::new())
-    }
-}
\ No newline at end of file
+// the type an expr of type TYPE is
+fn type_value(expr: &mut Expr, env: &mut Environment) -> Option<DType> {
+    let (bytes, dtype) = expr.interpret(env)?;
+    if !TYPE.is_assignable_from(&dtype) { return None }
+    let mut type_slice = [0; TYPE_BYTES];
+    fill_slice_with_vec(&mut type_slice, bytes);
+    Some(DType::from_bytes(type_slice))
+}
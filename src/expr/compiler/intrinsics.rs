@@ -0,0 +1,538 @@
+use std::{convert::TryInto, rc::Rc};
+use crate::{expr::Expr, token::{literal::Literal, span::{SourceMap, Span}}};
+use super::{DType, Environment, TypeCheck, core_lib::*, dtype::{Code, Intrinsic, Msg, Param}, fields};
+
+// `intrinsic: "I32"` is the type the compiler knows by that name. only the prelude can use it
+pub fn intrinsic_msg() -> Msg {
+    let constructor = |_: Option<Box<Expr>>, _: &Environment, args: Vec<Expr>| match args.first() {
+        Some(Expr::Literal(Literal::String(name, _), _)) if intrinsic_named(name).is_some() =>
+            intrinsic_named(name).unwrap().dtype().to_expr(),
+        // not a type, so it fails the return type check
+        _ => Expr::Object(vec![], Span::default()),
+    };
+    Msg::new("intrinsic".into(), Rc::new(constructor), TYPE, vec![Param::new("name".into(), STRING)])
+}
+
+fn intrinsic_named(name: &[u8]) -> Option<Intrinsic> {
+    std::str::from_utf8(name).ok().and_then(Intrinsic::from_name)
+}
+
+// msgs every value of an intrinsic type has. TODO: these belong in the prelude, see prelude.jup
+pub fn intrinsic_msgs(intrinsic: Intrinsic) -> Vec<Msg> {
+    match intrinsic {
+        Intrinsic::Bool => bool_msgs(),
+        Intrinsic::I32 => [int_msgs(Int::from_intrinsic(intrinsic).unwrap()), i32_float_msgs()].concat(),
+        Intrinsic::F32 => f32_msgs(),
+        Intrinsic::F64 => f64_msgs(),
+        _ => Int::from_intrinsic(intrinsic).map_or(vec![], int_msgs),
+    }
+}
+
+// the control structures, emitted on the stack. the code they run can be any expression, and
+// fn values are called instead of run in place
+pub fn control_msgs() -> Vec<Msg> {
+    let any = || DType::new(0, vec![], true, true);
+    let if_else = |_: Option<Box<Expr>>, _: &Environment, args: Vec<Expr>| asm_node(&VOID, format!(
+        " mov jreg8#c, j# {}\n test jreg1#c, jreg1#c\n jz jlabel#else\n{} jmp jlabel#end\njlabel#else:\n{}jlabel#end:\n",
+        args[0].to_syntax(), run(&args[1]), run(&args[2])
+    ));
+    let while_ = |_: Option<Box<Expr>>, _: &Environment, args: Vec<Expr>| asm_node(&VOID, format!(
+        "jloop# jlabel#top jlabel#end\njlabel#top:\n mov jreg8#c, j# {}\n test jreg1#c, jreg1#c\n jz jlabel#end\n\
+        {} jmp jlabel#top\njlabel#end:\n",
+        args[0].to_syntax(), run(&args[1])
+    ));
+    let loop_ = |_: Option<Box<Expr>>, _: &Environment, args: Vec<Expr>| asm_node(&VOID, format!(
+        "jloop# jlabel#top jlabel#end\njlabel#top:\n{} jmp jlabel#top\njlabel#end:\n", run(&args[0])
+    ));
+    // the labels are filled in by the innermost loop around these
+    let break_ = |_: Option<Box<Expr>>, _: &Environment, _: Vec<Expr>| asm_node(&VOID, " jmp jbreak#\n".into());
+    let continue_ = |_: Option<Box<Expr>>, _: &Environment, _: Vec<Expr>| asm_node(&VOID, " jmp jcontinue#\n".into());
+
+    vec![
+        Msg::new("if_else".into(), Rc::new(if_else), VOID, vec![
+            Param::new("cond".into(), BOOL),
+            Param::new("then".into(), any()).with_code(Code::Run),
+            Param::new("else".into(), any()).with_code(Code::Run)
+        ]),
+        Msg::new("while".into(), Rc::new(while_), VOID, vec![
+            Param::new("cond".into(), BOOL), Param::new("body".into(), any()).with_code(Code::Loop)
+        ]),
+        Msg::new("loop".into(), Rc::new(loop_), VOID, vec![Param::new("body".into(), any()).with_code(Code::Loop)]),
+        Msg::new("break".into(), Rc::new(break_), VOID, vec![]),
+        Msg::new("continue".into(), Rc::new(continue_), VOID, vec![]),
+    ]
+}
+// the asm that runs code, on a line of its own. a fn was already made a call to it when it was checked
+fn run(code: &Expr) -> String {
+    format!(" j# {}\n", code.to_syntax())
+}
+
+// the type of a fn, which is the address of its code. `f.!` calls it
+pub fn fn_type(params: Vec<Param>, ret_type: DType) -> DType {
+    DType { msgs: vec![call_msg(params, ret_type, false)], ..FN }
+}
+// the type of a fn that captured runtime values, which is called the same way
+pub fn closure_type(params: Vec<Param>, ret_type: DType) -> DType {
+    DType { msgs: vec![call_msg(params, ret_type, true)], ..CLOSURE }
+}
+// calls the fn by the calling convention in README.md. the args are put in a block at r11 as they're
+// computed, then copied to the top of the stack, above whatever computing them left there. r11
+// is saved with the other registers, so a call among the args leaves it as it was. the fn can
+// clobber r11, so the copy has a slot above it that keeps r11 for finding the pushed registers again
+fn call_msg(params: Vec<Param>, ret_type: DType, closure: bool) -> Msg {
+    let ret = ret_type.clone();
+    let arg_types: Vec<DType> = params.iter().map(|param| param.dtype.clone()).collect();
+    let constructor = move |self_expr: Option<Box<Expr>>, _: &Environment, args: Vec<Expr>| {
+        let args_size: u32 = arg_types.iter().map(fields::slot_size).sum();
+        // the fn's address goes above the block, with the record's above it for a closure
+        let (push_fn, pushed) = if closure {
+            (format!(" mov jreg8#c, j# {}\n push qword [jreg8#c+8]\n push qword [jreg8#c]\n", self_expr.unwrap().to_syntax()), 16)
+        } else { (format!(" push j# {}\n", self_expr.unwrap().to_syntax()), 8) };
+        let mut text = format!(
+            " push rax\n push rbx\n push rcx\n push rdx\n push r11\n{} sub rsp, {}\n mov r11, rsp\n", push_fn, args_size
+        );
+        let mut offset = 0;
+        for (arg, dtype) in args.iter().zip(arg_types.iter()) {
+            text.push_str(&fields::store_arg(dtype, "r11", offset, arg));
+            offset += fields::slot_size(dtype);
+        }
+        let r11_slot = args_size + pushed - 8;
+        text.push_str(&format!(" sub rsp, {}\n mov qword [rsp+{}], r11\n", r11_slot + 8, r11_slot));
+        for slot in (0..args_size).step_by(8) {
+            text.push_str(&format!(" mov jreg8#t, qword [r11+{}]\n mov qword [rsp+{}], jreg8#t\n", slot, slot));
+        }
+        if closure {
+            text.push_str(&format!(" mov jreg8#t, qword [r11+{}]\n mov qword [rsp+{}], jreg8#t\n", args_size + 8, args_size));
+        }
+        text.push_str(&format!(
+            " call qword [r11+{}]\n mov r11, qword [rsp+{}]\n lea rsp, [r11+{}]\n mov r10, rax\n pop r11\n pop rdx\n pop rcx\n \
+            pop rbx\n pop rax\n jret# {}( r10 )\n",
+            args_size, r11_slot, args_size + pushed, if fields::in_register(&ret) { "val" } else { "addr" }
+        ));
+        asm_node(&ret, text)
+    };
+    Msg::new("!".into(), Rc::new(constructor), ret_type, params)
+}
+
+fn bool_msgs() -> Vec<Msg> {
+    vec![
+        short_circuit("and", "jz", |a, b| Some(Literal::Bool(a && b))),
+        short_circuit("or", "jnz", |a, b| Some(Literal::Bool(a || b))),
+        unary("not", BOOL, " mov jreg8#a, j# {}\n xor jreg4#a, 1\n jret# val( jreg8#a )\n".into(), bool_from,
+            |a| Some(Literal::Bool(!a))),
+    ]
+}
+// the arg, usually a code block, only runs if jump doesn't skip it, leaving self as the result
+fn short_circuit(name: &str, jump: &'static str, fold: fn(bool, bool) -> Option<Literal>) -> Msg {
+    let constructor = move |self_expr: Option<Box<Expr>>, _: &Environment, args: Vec<Expr>| asm_node(&BOOL, format!(
+        " mov jreg8#r, j# {}\n test jreg1#r, jreg1#r\n {} jlabel#done\n mov jreg8#r, j# {}\njlabel#done:\n jret# val( jreg8#r )\n",
+        self_expr.unwrap().to_syntax(), jump, args[0].to_syntax()
+    ));
+    let fold = move |self_bytes: &[u8], args: &[Vec<u8>]| fold(bool_from(self_bytes)?, bool_from(args.first()?)?);
+    Msg::new(name.into(), Rc::new(constructor), BOOL, vec![Param::new("other".into(), BOOL)])
+        .with_fold(Rc::new(fold))
+}
+
+// what the msgs of an integer type need to know about it
+#[derive(Clone, Copy)]
+struct Int {
+    intrinsic: Intrinsic,
+    size: u32,
+    signed: bool,
+    // bytes only have the bitwise msgs
+    bits_only: bool,
+}
+impl Int {
+    const ALL: [Int; 12] = [
+        Int { intrinsic: Intrinsic::B8, size: 1, signed: false, bits_only: true },
+        Int { intrinsic: Intrinsic::B16, size: 2, signed: false, bits_only: true },
+        Int { intrinsic: Intrinsic::B32, size: 4, signed: false, bits_only: true },
+        Int { intrinsic: Intrinsic::B64, size: 8, signed: false, bits_only: true },
+        Int { intrinsic: Intrinsic::U8, size: 1, signed: false, bits_only: false },
+        Int { intrinsic: Intrinsic::U16, size: 2, signed: false, bits_only: false },
+        Int { intrinsic: Intrinsic::U32, size: 4, signed: false, bits_only: false },
+        Int { intrinsic: Intrinsic::U64, size: 8, signed: false, bits_only: false },
+        Int { intrinsic: Intrinsic::I8, size: 1, signed: true, bits_only: false },
+        Int { intrinsic: Intrinsic::I16, size: 2, signed: true, bits_only: false },
+        Int { intrinsic: Intrinsic::I32, size: 4, signed: true, bits_only: false },
+        Int { intrinsic: Intrinsic::I64, size: 8, signed: true, bits_only: false },
+    ];
+
+    fn from_intrinsic(intrinsic: Intrinsic) -> Option<Self> {
+        Self::ALL.iter().copied().find(|int| int.intrinsic == intrinsic)
+    }
+    fn bits(self) -> u32 {
+        self.size * 8
+    }
+    // as it appears in conversion msgs, like `as_i64`
+    fn name(self) -> String {
+        format!("{:?}", self.intrinsic).to_lowercase()
+    }
+    // every value of self fits in to
+    fn fits_in(self, to: Int) -> bool {
+        match (self.signed, to.signed) {
+            (false, false) | (true, true) => to.size >= self.size,
+            (false, true) => to.size > self.size,
+            (true, false) => false,
+        }
+    }
+
+    // a value of this size at addr
+    fn mem(self, addr: &str) -> String {
+        let size = match self.size {
+            1 => "byte",
+            2 => "word",
+            4 => "dword",
+            _ => "qword",
+        };
+        format!("{} [{}]", size, addr)
+    }
+    // loads src into the register alias, extended to all 64 bits so msgs can work on the whole register
+    fn load(self, alias: &str, src: &str) -> String {
+        match (self.signed, self.size) {
+            (_, 8) => format!(" mov jreg8#{}, {}\n", alias, src),
+            (true, 4) => format!(" movsxd jreg8#{}, {}\n", alias, src),
+            (true, _) => format!(" movsx jreg8#{}, {}\n", alias, src),
+            // writing the low dword zeroes the rest
+            (false, 4) => format!(" mov jreg4#{}, {}\n", alias, src),
+            (false, _) => format!(" movzx jreg4#{}, {}\n", alias, src),
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> Option<i128> {
+        if bytes.len() != self.size as usize { return None }
+        let mut val = [0; 16];
+        val[..bytes.len()].copy_from_slice(bytes);
+        Some(self.wrap(i128::from_le_bytes(val)))
+    }
+    // what is left of val in a register of this type
+    fn wrap(self, val: i128) -> i128 {
+        let shift = 128 - self.bits();
+        if self.signed { (val << shift) >> shift } else { ((val << shift) as u128 >> shift) as i128 }
+    }
+    fn literal(self, val: i128) -> Literal {
+        let val = self.wrap(val);
+        match self.intrinsic {
+            Intrinsic::B8 => Literal::Byte(val as u8),
+            Intrinsic::B16 => Literal::B16(val as u16),
+            Intrinsic::B32 => Literal::B32(val as u32),
+            Intrinsic::B64 => Literal::B64(val as u64),
+            Intrinsic::U8 => Literal::U8(val as u8),
+            Intrinsic::U16 => Literal::U16(val as u16),
+            Intrinsic::U32 => Literal::U32(val as u32),
+            Intrinsic::U64 => Literal::U64(val as u64),
+            Intrinsic::I8 => Literal::I8(val as i8),
+            Intrinsic::I16 => Literal::I16(val as i16),
+            Intrinsic::I32 => Literal::Integer(val as i32),
+            _ => Literal::I64(val as i64),
+        }
+    }
+}
+
+// operands are extended to 64 bits and worked on there. only the low bits of the result are
+// used, which wrap the same as they would at the type's size
+fn int_msgs(int: Int) -> Vec<Msg> {
+    // both operands are on the stack, self under the arg
+    let operands = format!("{}{}", int.load("a", &int.mem("rsp+8")), int.load("b", &int.mem("rsp")));
+    let op = |op: &str| format!("{} add rsp, 16\n {} jreg8#a, jreg8#b\n jret# val( jreg8#a )\n", operands, op);
+    let cmp = |set: &str| format!(
+        "{} add rsp, 16\n cmp jreg8#a, jreg8#b\n {} jreg1#a\n movzx jreg4#a, jreg1#a\n jret# val( jreg8#a )\n", operands, set
+    );
+    // div and the shifts need particular registers, so save them and work on the stack
+    let extended = format!("{} mov qword [rsp+8], jreg8#a\n mov qword [rsp], jreg8#b\n", operands);
+    let div = |result: &str| format!(
+        "{} push rax\n push rdx\n mov rax, qword [rsp+24]\n {}\n {} qword [rsp+16]\n mov qword [rsp+16], {}\n \
+        pop rdx\n pop rax\n jret# val( qword [rsp] )\n add rsp, 16\n",
+        extended, if int.signed { "cqo" } else { "xor edx, edx" }, if int.signed { "idiv" } else { "div" }, result
+    );
+    // like x86, only the low bits of the count that can shift within the type are used
+    let shift = |op: &str| format!(
+        "{} push rcx\n mov ecx, dword [rsp+8]\n and ecx, {}\n {} qword [rsp+16], cl\n pop rcx\n jret# val( qword [rsp+8] )\n \
+        add rsp, 16\n", extended, int.bits() - 1, op
+    );
+    let int_binary = |name, ret_type, body, fold: fn(Int, i128, i128) -> Option<Literal>|
+        binary(name, int.intrinsic.dtype(), ret_type, body, move |bytes| int.decode(bytes), move |a, b| fold(int, a, b));
+    let (less, greater) = if int.signed { ("l", "g") } else { ("b", "a") };
+
+    let mut msgs = vec![
+        int_binary("&", int.intrinsic.dtype(), op("and"), |int, a, b| Some(int.literal(a & b))),
+        int_binary("|", int.intrinsic.dtype(), op("or"), |int, a, b| Some(int.literal(a | b))),
+        int_binary("^", int.intrinsic.dtype(), op("xor"), |int, a, b| Some(int.literal(a ^ b))),
+        int_binary("<<", int.intrinsic.dtype(), shift("shl"), |int, a, b| Some(int.literal(a << (b & (int.bits() as i128 - 1))))),
+        int_binary(">>", int.intrinsic.dtype(), shift(if int.signed { "sar" } else { "shr" }),
+            |int, a, b| Some(int.literal(a >> (b & (int.bits() as i128 - 1))))),
+        int_binary("==", BOOL, cmp("sete"), |_, a, b| Some(Literal::Bool(a == b))),
+        int_binary("!=", BOOL, cmp("setne"), |_, a, b| Some(Literal::Bool(a != b))),
+    ];
+    if !int.bits_only {
+        msgs.extend(vec![
+            int_binary("+", int.intrinsic.dtype(), op("add"), |int, a, b| Some(int.literal(a + b))),
+            int_binary("-", int.intrinsic.dtype(), op("sub"), |int, a, b| Some(int.literal(a - b))),
+            int_binary("*", int.intrinsic.dtype(), op("imul"), |int, a, b| Some(int.literal(a.wrapping_mul(b)))),
+            // dividing by zero, or overflowing a 64 bit quotient, is left to fault at runtime
+            int_binary("/", int.intrinsic.dtype(), div("rax"), |int, a, b| div_fold(int, a, b, a.checked_div(b)?)),
+            int_binary("%", int.intrinsic.dtype(), div("rdx"), |int, a, b| div_fold(int, a, b, a.checked_rem(b)?)),
+            int_binary("<", BOOL, cmp(&format!("set{}", less)), |_, a, b| Some(Literal::Bool(a < b))),
+            int_binary("<=", BOOL, cmp(&format!("set{}e", less)), |_, a, b| Some(Literal::Bool(a <= b))),
+            int_binary(">", BOOL, cmp(&format!("set{}", greater)), |_, a, b| Some(Literal::Bool(a > b))),
+            int_binary(">=", BOOL, cmp(&format!("set{}e", greater)), |_, a, b| Some(Literal::Bool(a >= b))),
+        ]);
+    }
+    if int.signed {
+        msgs.push(unary("neg", int.intrinsic.dtype(),
+            " mov jreg8#a, j# {}\n neg jreg8#a\n jret# val( jreg8#a )\n".into(),
+            move |bytes| int.decode(bytes), move |a| Some(int.literal(-a))));
+    }
+    // `as_` only converts to types that hold every value, anything else has to say it might lose some
+    for to in Int::ALL.iter().copied().filter(|to| to.intrinsic != int.intrinsic) {
+        let name = format!("{}_{}", if int.fits_in(to) { "as" } else { "truncate" }, to.name());
+        let body = format!(" mov jreg8#a, j# {{}}\n{} jret# val( jreg8#a )\n",
+            int.load("a", &format!("jreg{}#a", int.size)));
+        msgs.push(unary(&name, to.intrinsic.dtype(), body, move |bytes| int.decode(bytes), move |a| Some(to.literal(a))));
+    }
+    msgs
+}
+// the quotient of the 64 bit types can overflow, which faults
+fn div_fold(int: Int, a: i128, b: i128, result: i128) -> Option<Literal> {
+    if int.size == 8 && int.wrap(a / b) != a / b { return None }
+    Some(int.literal(result))
+}
+
+fn i32_float_msgs() -> Vec<Msg> {
+    vec![
+        unary("as_f32", F32, " push j# {}\n cvtsi2ss jxmm#a, dword [rsp]\n add rsp, 8\n movd jreg4#r, jxmm#a\n \
+            jret# val( jreg8#r )\n".into(), i32_from, |a| f32_lit(a as f32)),
+        unary("as_f64", F64, " push j# {}\n cvtsi2sd jxmm#a, dword [rsp]\n add rsp, 8\n movq jreg8#r, jxmm#a\n \
+            jret# val( jreg8#r )\n".into(), i32_from, |a| f64_lit(a as f64)),
+    ]
+}
+
+fn f32_msgs() -> Vec<Msg> {
+    let f32_binary = |name, ret_type, body, fold: fn(f32, f32) -> Option<Literal>| binary(name, F32, ret_type, body, f32_from, fold);
+    vec![
+        f32_binary("+", F32, float_op("addss", "movss", "dword", "movd"), |a, b| f32_lit(a + b)),
+        f32_binary("-", F32, float_op("subss", "movss", "dword", "movd"), |a, b| f32_lit(a - b)),
+        f32_binary("*", F32, float_op("mulss", "movss", "dword", "movd"), |a, b| f32_lit(a * b)),
+        f32_binary("/", F32, float_op("divss", "movss", "dword", "movd"), |a, b| f32_lit(a / b)),
+        f32_binary("==", BOOL, float_eq("ucomiss", "movss", "dword", false), |a, b| Some(Literal::Bool(a == b))),
+        f32_binary("!=", BOOL, float_eq("ucomiss", "movss", "dword", true), |a, b| Some(Literal::Bool(a != b))),
+        f32_binary("<", BOOL, float_cmp("ucomiss", "movss", "dword", "seta", true), |a, b| Some(Literal::Bool(a < b))),
+        f32_binary("<=", BOOL, float_cmp("ucomiss", "movss", "dword", "setae", true), |a, b| Some(Literal::Bool(a <= b))),
+        f32_binary(">", BOOL, float_cmp("ucomiss", "movss", "dword", "seta", false), |a, b| Some(Literal::Bool(a > b))),
+        f32_binary(">=", BOOL, float_cmp("ucomiss", "movss", "dword", "setae", false), |a, b| Some(Literal::Bool(a >= b))),
+        // flipping the sign bit doesn't need sse
+        unary("neg", F32, " mov jreg8#a, j# {}\n btc jreg4#a, 31\n jret# val( jreg8#a )\n".into(), f32_from, |a| f32_lit(-a)),
+        unary("as_f64", F64, " push j# {}\n cvtss2sd jxmm#a, dword [rsp]\n add rsp, 8\n movq jreg8#r, jxmm#a\n \
+            jret# val( jreg8#r )\n".into(), f32_from, |a| f64_lit(a as f64)),
+        // rounds toward zero
+        unary("as_i32", I32, " push j# {}\n cvttss2si jreg4#r, dword [rsp]\n add rsp, 8\n jret# val( jreg8#r )\n".into(),
+            f32_from, |a| i32_lit(a as f64)),
+    ]
+}
+
+fn f64_msgs() -> Vec<Msg> {
+    let f64_binary = |name, ret_type, body, fold: fn(f64, f64) -> Option<Literal>| binary(name, F64, ret_type, body, f64_from, fold);
+    vec![
+        f64_binary("+", F64, float_op("addsd", "movsd", "qword", "movq"), |a, b| f64_lit(a + b)),
+        f64_binary("-", F64, float_op("subsd", "movsd", "qword", "movq"), |a, b| f64_lit(a - b)),
+        f64_binary("*", F64, float_op("mulsd", "movsd", "qword", "movq"), |a, b| f64_lit(a * b)),
+        f64_binary("/", F64, float_op("divsd", "movsd", "qword", "movq"), |a, b| f64_lit(a / b)),
+        f64_binary("==", BOOL, float_eq("ucomisd", "movsd", "qword", false), |a, b| Some(Literal::Bool(a == b))),
+        f64_binary("!=", BOOL, float_eq("ucomisd", "movsd", "qword", true), |a, b| Some(Literal::Bool(a != b))),
+        f64_binary("<", BOOL, float_cmp("ucomisd", "movsd", "qword", "seta", true), |a, b| Some(Literal::Bool(a < b))),
+        f64_binary("<=", BOOL, float_cmp("ucomisd", "movsd", "qword", "setae", true), |a, b| Some(Literal::Bool(a <= b))),
+        f64_binary(">", BOOL, float_cmp("ucomisd", "movsd", "qword", "seta", false), |a, b| Some(Literal::Bool(a > b))),
+        f64_binary(">=", BOOL, float_cmp("ucomisd", "movsd", "qword", "setae", false), |a, b| Some(Literal::Bool(a >= b))),
+        unary("neg", F64, " mov jreg8#a, j# {}\n btc jreg8#a, 63\n jret# val( jreg8#a )\n".into(), f64_from, |a| f64_lit(-a)),
+        unary("as_f32", F32, " push j# {}\n cvtsd2ss jxmm#a, qword [rsp]\n add rsp, 8\n movd jreg4#r, jxmm#a\n \
+            jret# val( jreg8#r )\n".into(), f64_from, |a| f32_lit(a as f32)),
+        unary("as_i32", I32, " push j# {}\n cvttsd2si jreg4#r, qword [rsp]\n add rsp, 8\n jret# val( jreg8#r )\n".into(),
+            f64_from, i32_lit),
+    ]
+}
+// self is at [rsp+8] and the arg at [rsp]. the result goes back through a general purpose register
+fn float_op(op: &str, mov: &str, size: &str, mov_out: &str) -> String {
+    format!(
+        " {mov} jxmm#a, {size} [rsp+8]\n {op} jxmm#a, {size} [rsp]\n add rsp, 16\n {mov_out} jreg{n}#r, jxmm#a\n jret# val( jreg8#r )\n",
+        mov = mov, size = size, op = op, mov_out = mov_out, n = if size == "dword" { 4 } else { 8 }
+    )
+}
+// comparing with nan is unordered, which sets the carry flag, so `<` is `>` with the operands
+// swapped to come out false
+fn float_cmp(ucomis: &str, mov: &str, size: &str, set: &str, swapped: bool) -> String {
+    let (left, right) = if swapped { ("[rsp]", "[rsp+8]") } else { ("[rsp+8]", "[rsp]") };
+    format!(
+        " {mov} jxmm#a, {size} {left}\n {ucomis} jxmm#a, {size} {right}\n add rsp, 16\n {set} jreg1#r\n \
+        movzx jreg4#r, jreg1#r\n jret# val( jreg8#r )\n",
+        mov = mov, size = size, left = left, ucomis = ucomis, right = right, set = set
+    )
+}
+// unordered sets the zero flag too, so the parity flag has to say it wasn't
+fn float_eq(ucomis: &str, mov: &str, size: &str, negated: bool) -> String {
+    let (set, set_parity, combine) = if negated { ("setne", "setp", "or") } else { ("sete", "setnp", "and") };
+    format!(
+        " {mov} jxmm#a, {size} [rsp+8]\n {ucomis} jxmm#a, {size} [rsp]\n add rsp, 16\n {set} jreg1#r\n {set_parity} jreg1#p\n \
+        {combine} jreg1#r, jreg1#p\n movzx jreg4#r, jreg1#r\n jret# val( jreg8#r )\n",
+        mov = mov, size = size, ucomis = ucomis, set = set, set_parity = set_parity, combine = combine
+    )
+}
+
+// pushes self and the arg, then runs body
+fn binary<T>(name: &str, operand_type: DType, ret_type: DType, body: String,
+    decode: impl Fn(&[u8]) -> Option<T> + 'static, fold: impl Fn(T, T) -> Option<Literal> + 'static
+) -> Msg {
+    let asm_ret_type = ret_type.clone();
+    let constructor = move |self_expr: Option<Box<Expr>>, _: &Environment, args: Vec<Expr>| asm_node(
+        &asm_ret_type,
+        format!(" push j# {}\n push j# {}\n{}", self_expr.unwrap().to_syntax(), args[0].to_syntax(), body)
+    );
+    let fold = move |self_bytes: &[u8], args: &[Vec<u8>]| fold(decode(self_bytes)?, decode(args.first()?)?);
+    Msg::new(name.into(), Rc::new(constructor), ret_type, vec![Param::new("other".into(), operand_type)])
+        .with_fold(Rc::new(fold))
+}
+// body has a `{}` where self goes
+fn unary<T>(name: &str, ret_type: DType, body: String,
+    decode: impl Fn(&[u8]) -> Option<T> + 'static, fold: impl Fn(T) -> Option<Literal> + 'static
+) -> Msg {
+    let asm_ret_type = ret_type.clone();
+    let constructor = move |self_expr: Option<Box<Expr>>, _: &Environment, _: Vec<Expr>|
+        asm_node(&asm_ret_type, body.replace("{}", &self_expr.unwrap().to_syntax()));
+    let fold = move |self_bytes: &[u8], _: &[Vec<u8>]| fold(decode(self_bytes)?);
+    Msg::new(name.into(), Rc::new(constructor), ret_type, vec![]).with_fold(Rc::new(fold))
+}
+pub fn asm_node(ret_type: &DType, text: String) -> Expr {
+    Expr::Asm(
+        Box::new(Expr::Object(vec![], Span::default())),
+        Box::new(ret_type.to_expr()),
+        Box::new(Expr::Literal(Literal::String(text.into_bytes(), SourceMap::default()), Span::default())),
+        Span::default()
+    )
+}
+
+// the literal for a static value of an intrinsic type, if it has one
+pub fn literal_from_bytes(dtype: &DType, bytes: &[u8]) -> Option<Literal> {
+    let intrinsic = dtype.intrinsic?;
+    if let Some(int) = Int::from_intrinsic(intrinsic) { return Some(int.literal(int.decode(bytes)?)) }
+    match intrinsic {
+        Intrinsic::Bool => bool_from(bytes).map(Literal::Bool),
+        Intrinsic::F32 => f32_lit(f32_from(bytes)?),
+        Intrinsic::F64 => f64_lit(f64_from(bytes)?),
+        _ => None,
+    }
+}
+
+fn bool_from(bytes: &[u8]) -> Option<bool> {
+    match bytes {
+        [byte] => Some(*byte != 0),
+        _ => None,
+    }
+}
+fn i32_from(bytes: &[u8]) -> Option<i32> {
+    Some(i32::from_le_bytes(bytes.try_into().ok()?))
+}
+fn f32_from(bytes: &[u8]) -> Option<f32> {
+    Some(f32::from_le_bytes(bytes.try_into().ok()?))
+}
+fn f64_from(bytes: &[u8]) -> Option<f64> {
+    Some(f64::from_le_bytes(bytes.try_into().ok()?))
+}
+// infinities and nans have no literal to fold into
+fn f32_lit(val: f32) -> Option<Literal> {
+    if val.is_finite() { Some(Literal::Float(val)) } else { None }
+}
+fn f64_lit(val: f64) -> Option<Literal> {
+    if val.is_finite() { Some(Literal::F64(val)) } else { None }
+}
+// out of range conversions are left to runtime, where they give i32::MIN
+fn i32_lit(val: f64) -> Option<Literal> {
+    if val > i32::MIN as f64 - 1.0 && val < i32::MAX as f64 + 1.0 { Some(Literal::Integer(val as i32)) } else { None }
+}
+#[cfg(test)]
+mod tests {
+    use crate::{expr::{Expr, test_support::parse}, token::literal::Literal};
+    use super::{TypeCheck, Environment};
+
+    fn check(source: &str) -> Expr {
+        let mut expr = parse(source);
+        expr.check(&mut Environment::new()).unwrap();
+        expr
+    }
+    // the literal a static expression folds into
+    fn folded(source: &str) -> Literal {
+        match check(&format!("{{{}}}", source)) {
+            Expr::CodeBlock(mut exprs, _) => match exprs.pop() {
+                Some(Expr::Literal(lit, _)) => lit,
+                expr => panic!("{} didn't fold: {:?}", source, expr),
+            },
+            expr => panic!("{:?}", expr),
+        }
+    }
+    // the asm a msg to a runtime value constructs
+    fn asm(source: &str) -> String {
+        check(&format!("{{{}}}", source)).to_syntax()
+    }
+
+    #[test]
+    fn float_msgs() {
+        let cases = [
+            ("1.5 + 2.25", Literal::Float(3.75)),
+            ("1.5 * 2.0 - 0.5", Literal::Float(2.5)),
+            ("1.0 / 4.0", Literal::Float(0.25)),
+            ("1.5.neg", Literal::Float(-1.5)),
+            ("1.5f64 + 2.25f64", Literal::F64(3.75)),
+            ("3.0f64 / 2.0f64", Literal::F64(1.5)),
+            ("1.5.as_f64", Literal::F64(1.5)),
+            ("2.75f64.as_f32", Literal::Float(2.75)),
+            ("7.as_f32", Literal::Float(7.0)),
+            ("-2.75.as_i32", Literal::Integer(-2)),
+            ("2.75f64.as_i32", Literal::Integer(2)),
+            ("1.5 < 2.0", Literal::Bool(true)),
+            ("1.5 >= 2.0", Literal::Bool(false)),
+            ("2.0f64 == 2.0f64", Literal::Bool(true)),
+        ];
+        for (source, lit) in cases.iter() {
+            assert_eq!(&folded(source), lit, "{}", source);
+        }
+
+        // infinities, nans and out of range conversions are left to runtime
+        for source in ["1.0 / 0.0", "{0.0 / 0.0} == {0.0 / 0.0}", "3e9.as_i32"].iter() {
+            assert!(asm(source).contains("asm "), "{} folded", source);
+        }
+
+        // a runtime operand goes through sse
+        let f32_operand = "{asm [] F32 \"jret# val( rax )\"}";
+        assert!(asm(&format!("{} + 1.5", f32_operand)).contains("addss"));
+        assert!(asm(&format!("{} < 1.5", f32_operand)).contains("ucomiss"));
+        assert!(asm(&format!("{}.as_f64", f32_operand)).contains("cvtss2sd"));
+        let f64_operand = "{asm [] F64 \"jret# val( rax )\"}";
+        assert!(asm(&format!("{} * 2.0f64", f64_operand)).contains("mulsd"));
+        assert!(asm(&format!("{} != 2.0f64", f64_operand)).contains("setp"));
+    }
+
+    #[test]
+    fn bool_msgs() {
+        let cases = [
+            ("true.and: {false}", false),
+            ("true.and: true", true),
+            ("false.or: {true}", true),
+            ("false.or: false", false),
+            ("true.not", false),
+            ("{1 < 2}.and: {2.0 > 3.0}.not", true),
+            ("{1 == 2}.or: {3 != 4}", true),
+        ];
+        for (source, val) in cases.iter() {
+            assert_eq!(folded(source), Literal::Bool(*val), "{}", source);
+        }
+
+        // the arg only runs when self doesn't already say what the result is
+        let operand = "{asm [] Bool \"jret# val( rax )\"}";
+        let arg = "{asm [] Bool \" ; the arg\n jret# val( rbx )\"}";
+        for (msg, jump) in [("and", "jz jlabel#done"), ("or", "jnz jlabel#done")].iter() {
+            let text = asm(&format!("{}.{}: {}", operand, msg, arg));
+            let (jump_at, arg_at) = (text.find(jump), text.find("; the arg"));
+            assert!(jump_at.is_some() && jump_at < arg_at, "{}", text);
+            assert!(text.find("jlabel#done:") > arg_at, "{}", text);
+        }
+        assert!(asm(&format!("{}.not", operand)).contains("xor"));
+    }
+}
@@ -4,16 +4,18 @@ mod interpreter;
 mod type_checker;
 mod decl;
 pub mod core_lib;
+mod intrinsics;
+mod fields;
+mod infer;
 pub mod code_generator;
 
 pub use env::Environment;
 use dtype::DType;
 use super::Expr;
 
-pub use type_checker::TypeCheck;
+pub use type_checker::{TypeCheck, TypeError};
 
 fn fill_slice_with_vec<T: Clone>(slice: &mut [T], vec: Vec<T>) {
-    for i in 0..slice.len() {
-        slice[i] = vec[i].clone();
-    }
+    let len = slice.len();
+    slice.clone_from_slice(&vec[..len]);
 }
\ No newline at end of file
@@ -1,10 +1,9 @@
 use std::{fmt::{Debug, Display}, rc::Rc};
-use crate::{expr::parser::Parser, pprint::PPrint, token::{Token, TokenType, literal::Literal, scanner::Scanner}};
-use super::{Expr, core_lib::*, decl::Decl, dtype::{DType, Msg}, env::Environment, fill_slice_with_vec, interpreter::Interpret};
+use crate::{expr::{module::Module, parser::Parser}, pprint::PPrint, scanner::Scanner, token::{Token, TokenType, literal::Literal, span::{SourceMap, Span}}};
+use super::{Expr, core_lib::*, decl::Decl, dtype::{Code, DType, Intrinsic, Msg, Param}, env::Environment, fields, fill_slice_with_vec, infer::{self, TypeVar}, interpreter::Interpret, intrinsics::{closure_type, fn_type, literal_from_bytes}};
 
 pub trait TypeCheck {
     fn check(&mut self, env: &mut Environment) -> Result<DType, TypeError>;
-    fn check_new_env(&mut self) -> Result<DType, TypeError>;
     fn to_syntax(&self) -> String;
 }
 
@@ -12,49 +11,90 @@ impl TypeCheck for Expr {
     fn check(&mut self, env: &mut Environment) -> Result<DType, TypeError> {
         match self {
             Expr::Binary(left, op, right) => if op.ttype == TokenType::Equal {
+                let declared_at = infer::declared_at(left);
                 let (decl_bytes, decl_type) = match left.interpret(env) {
                     Some(v) => v,
                     None => return Err(TypeError::new("expected static expression".into(), Some(op.clone()))),
                 };
-                if decl_type != DECL { return Err(TypeError::new("expected declaration expression".into(), Some(op.clone()))) }
+                if !DECL.is_assignable_from(&decl_type) { return Err(TypeError::new("expected declaration expression".into(), Some(op.clone()))) }
                 
-                let mut decl_slice = [0; 22];
+                let mut decl_slice = [0; DECL_BYTES];
                 fill_slice_with_vec(&mut decl_slice, decl_bytes);
                 let decl = match Decl::from_bytes(decl_slice, env) {
                     Some(v) => v,
                     None => return Err(TypeError::new("cannot get declaration name from stack".into(), Some(op.clone()))),
                 };
 
-                decl.initialize(right, env)
+                let span = left.span().to(&right.span());
+                let (dtype, store) = decl.initialize(right, declared_at, env)?;
+                // a local is stored in its slot instead of wherever its value was left
+                if let Some(mut store) = store {
+                    store.stamp(span);
+                    *self = store;
+                }
+                Ok(dtype)
+            } else if op.ttype == TokenType::RightArrow {
+                let span = self.span();
+                self.interpret(env).ok_or(TypeError::at("expected types on both sides of ->".into(), span))?;
+                Ok(TYPE)
             } else { panic!("unexpected binary operator") },
-            Expr::MsgEmission(self_opt, msg_name, arg_opt) => {
+            Expr::MsgEmission(self_opt, msg_name, args) => {
+                let start = self_opt.as_ref().map_or(msg_name.span, |inner| inner.span());
+                let span = start.to(&args.last().map_or(msg_name.span, |arg| arg.span()));
+                // a declared value's uses are what its type is inferred from
+                let self_var = self_opt.as_deref().and_then(declared_name);
                 let self_t = match self_opt {
                     Some(inner) => inner.check(env)?,
                     None => env.get_rt_stack_type(),
                 };
                 match self_t.get_msg(&msg_name.lexeme) {
                     Some(msg) => {
-                        // check if arg matched msg's arg type
-                        if let Some(arg) = arg_opt {
-                            match &msg.arg_type {
-                                Some(arg_type) => if &arg.check(env)? != arg_type
-                                    { return Err(TypeError::new("argument is of incorrect type".into(), Some(msg_name.clone()))) },
-                                None => return Err(TypeError::new("argument passed when not expected".into(), Some(msg_name.clone()))),
+                        if let Some(name) = self_var {
+                            let needed = DType::new(0, vec![msg.clone()], true, true);
+                            env.constrain(&name, &format!("{}.{}", name, msg_name.lexeme), &needed, start)?;
+                        }
+                        // check args against msg's params by position
+                        if args.len() != msg.params.len() {
+                            return Err(TypeError::new(
+                                format!("expected {} argument(s) to {}, found {}", msg.params.len(), msg_name.lexeme, args.len()),
+                                Some(msg_name.clone())
+                            ))
+                        }
+                        for (i, (arg, param)) in args.iter_mut().zip(msg.params.iter()).enumerate() {
+                            let (arg_var, arg_span) = (declared_name(arg), arg.span());
+                            // a param of a fn type may only be named by where it is
+                            let param_name = if param.name == "_" { (i + 1).to_string() } else { param.name.clone() };
+                            let mut arg_t = match param.code {
+                                Code::Value => arg.check(env)?,
+                                Code::Run => check_code(arg, env)?,
+                                Code::Loop => env.in_loop(|env| check_code(arg, env))?,
                             };
-                        } else {
-                            if let Some(_) = msg.arg_type {
-                                return Err(TypeError::new("expected argument".into(), Some(msg_name.clone())))
+                            if let Some(name) = arg_var {
+                                let use_name = format!("argument {} of {}", param_name, msg_name.lexeme);
+                                arg_t = env.constrain(&name, &use_name, &param.dtype, arg_span)?.unwrap_or(arg_t);
+                            }
+                            if !param.dtype.is_assignable_from(&arg_t) {
+                                return Err(TypeError::at(format!("argument {} is of incorrect type", param_name), arg.span()))
                             }
                         }
 
-                        let mut constructed_expr = msg.construct(self_opt.clone(), env, arg_opt.clone());
+                        // operands with static values go into the constructed expr as literals
+                        if let Some(inner) = self_opt { inline_static(inner, &self_t, env) }
+                        for (arg, param) in args.iter_mut().zip(msg.params.iter()) { inline_static(arg, &param.dtype, env) }
+                        if let Some(lit) = msg.fold(self_opt, args, env) {
+                            *self = Expr::Literal(lit, span);
+                            return Ok(msg.ret_type)
+                        }
+                        let mut constructed_expr = msg.construct(self_opt.clone(), env, args.clone());
+                        constructed_expr.stamp(span);
                         let dtype = constructed_expr.check(env)?;
-                        if dtype != msg.ret_type { return Err(TypeError::new("incorrect type of constructed expression".into(), Some(msg_name.clone()))) }
+                        if !msg.ret_type.is_assignable_from(&dtype) { return Err(TypeError::new("incorrect type of constructed expression".into(), Some(msg_name.clone()))) }
                         *self = constructed_expr;
-                        Ok(dtype)
+                        // the constructed expr may only be bytes, so the msg's type knows more about it
+                        Ok(msg.ret_type)
                     },
                     None => Err(TypeError::new(
-                        format!("object of type {:?} has no msg {}", self_t, msg_name.lexeme),
+                        format!("object of type {} has no msg {}", self_t, msg_name.lexeme),
                         Some(msg_name.clone())
                     )),
                 }
@@ -64,92 +104,112 @@ impl TypeCheck for Expr {
                     Ok(DECL)
                 } else { panic!("unexpected binary_opt operator") }
             },
-            Expr::Asm(asm_type, ret_type, text_expr) => { // 1 future TODO
-                let mut text = match *text_expr.clone() {
-                    Expr::Literal(Literal::String(string)) => string,
+            Expr::Asm(asm_type, ret_type, text_expr, span) => { // 1 future TODO
+                let text = match *text_expr.clone() {
+                    Expr::Literal(Literal::String(bytes, _), _) => String::from_utf8(bytes)
+                        .map_err(|_| TypeError::at("asm text must be valid UTF-8".into(), text_expr.span()))?,
                     _ => match text_expr.interpret(env) { // TODO: if string literal, get string directly
-                        Some((text_bytes, text_type)) => if text_type == STRING {
+                        Some((text_bytes, text_type)) => if STRING.is_assignable_from(&text_type) {
                             if text_bytes.len() as u32 == STRING.size {
-                                let mut text_slice: [u8; 16] = [0; 16];
+                                let mut text_slice = [0; STRING_BYTES];
                                 fill_slice_with_vec(&mut text_slice, text_bytes);
                                 str_from_jstr(text_slice, env).expect("could not get string from stack")
                             }
                             else { panic!("jstr is of incorrect size") }
-                        } else { return Err(TypeError::new("expected string".into(), None)) },
-                        None => return Err(TypeError::new("expected static expression".into(), None))
+                        } else { return Err(TypeError::at("expected string".into(), text_expr.span())) },
+                        None => return Err(TypeError::at("expected static expression".into(), text_expr.span()))
                     }
                 };
+                // where the text is in the source, if it was written out as a literal
+                let text_span = text_expr.span();
+                let text_map = match &**text_expr {
+                    Expr::Literal(Literal::String(_, map), _) => map.clone(),
+                    _ => SourceMap::default(),
+                };
 
-                **asm_type = Expr::Object(vec![]); // TODO: handle asm types
+                **asm_type = Expr::Object(vec![], *span); // TODO: handle asm types
                 
                 // check embedded jovis expressions
-                for (i,_) in text.clone().match_indices("j#") {
-                    let mut scanner  = Scanner::new(text.get((i+2)..).unwrap().to_string());
+                let mut checked_text = String::new();
+                let mut copied = 0;
+                // where the embedded expressions are in text
+                let mut embedded = vec![];
+                for (i,_) in text.match_indices("j#") {
+                    if i < copied { continue } // part of the previous expression
+                    // what's embedded in text that isn't in the source is placed at the asm
+                    let origin = text_map.locate(text.as_bytes(), i+2).unwrap_or(*span);
+                    let mut scanner  = Scanner::with_origin(text[(i+2)..].to_string(), origin);
                     let tokens = scanner.scan_tokens_err_ignore();
                     let mut parser = Parser::new(tokens);
-                    let (mut expr, last_token) = parser.parse_and_last_token();
-                    let n = last_token.start + last_token.lexeme.len();
+                    let (mut expr, last_token) = parser.parse_and_last_token().map_err(|errors|
+                        TypeError::at(errors[0].msg.clone(), errors[0].span)
+                    )?;
+                    let n = last_token.span.end - origin.start;
 
-                    expr.check(env)?;
-                    text.replace_range((i+2)..(n+i+2), expr.to_syntax().as_str());
+                    // what's after a loop's marker is in the loop
+                    let outside = |i: usize| !embedded.iter().any(|expr: &std::ops::Range<usize>| expr.contains(&i));
+                    if text[..i].match_indices("jloop#").any(|(j, _)| outside(j)) {
+                        env.in_loop(|env| expr.check(env))?;
+                    } else {
+                        expr.check(env)?;
+                    }
+                    checked_text.push_str(&text[copied..(i+2)]);
+                    checked_text.push_str(expr.to_syntax().as_str());
+                    copied = i+2+n;
+                    embedded.push(i..copied);
                 }
+                checked_text.push_str(&text[copied..]);
+                let outside = |i: usize| !embedded.iter().any(|expr| expr.contains(&i));
+                let at = |i| text_map.locate(text.as_bytes(), i).unwrap_or(*span);
+                check_asm_regs(&text, outside, at)?;
+                check_asm_loops(&text, outside, at, env.can_break())?;
+                let text = checked_text;
                 // check return expressions
-                for (i,_) in text.clone().match_indices("jret#") {
-                    let mut n = match text.get((i+5)..) {
-                        Some(text) => if let Some((i, _)) = text.match_indices("addr(").next() { i }
-                        else if let Some((i, _)) = text.match_indices("val(").next() { i }
-                        else { return Err(TypeError::new("expected 'addr' or 'val'".into(), None)) },
-                        None => panic!("value of i+5 seems to be too large"),
+                for (i,_) in text.match_indices("jret#") {
+                    let ret_text = &text[(i+5)..];
+                    let operand_start = match ret_text.find("addr(").or_else(|| ret_text.find("val(")) {
+                        Some(n) => n,
+                        None => return Err(TypeError::at("expected 'addr' or 'val'".into(), text_span)),
                     };
-                    loop {
-                        let c = match text.chars().nth(n) {
-                            Some(c) => c,
-                            None => panic!("unterminated jret expression in asm"),
-                        };
-                        if c == ')' { break };
-                        n += 1;
+                    if !ret_text[operand_start..].contains(')') {
+                        return Err(TypeError::at("unterminated jret expression in asm".into(), text_span))
                     }
                 }
                 
-                **text_expr = Expr::Literal(Literal::String(text));
+                **text_expr = Expr::Literal(Literal::String(text.into_bytes(), SourceMap::default()), text_span);
 
-                let mut ret_type_slice = [0; 6];
+                let mut ret_type_slice = [0; TYPE_BYTES];
                 let ret_type_bytes = ret_type.interpret(env)
-                    .ok_or(TypeError::new("expected static expression for asm return type".into(), None))?.0;
+                    .ok_or(TypeError::at("expected static expression for asm return type".into(), ret_type.span()))?.0;
                 fill_slice_with_vec(&mut ret_type_slice, ret_type_bytes);
                 let ret_type = DType::from_bytes(ret_type_slice);
 
                 Ok(ret_type)
             },
-            Expr::Object(exprs) => {
+            Expr::Import(path, span) => env.get_import(path)
+                .ok_or(TypeError::at(format!("module {} has not been loaded", path), *span)),
+            Expr::Object(exprs, _) => {
                 let mut size = 0;
                 let mut msgs = vec![];
                 for expr in exprs {
                     match expr {
                         Expr::Binary(left, op, right) => if op.ttype == TokenType::Equal {
+                            let declared_at = infer::declared_at(left);
                             let (decl_bytes, decl_type) = left.interpret(env)
                                 .ok_or(TypeError::new("expected static expression".into(), Some(op.clone())))?;
-                            if decl_type != DECL { return Err(TypeError::new("expected declaration expression".into(), Some(op.clone()))) }
+                            if !DECL.is_assignable_from(&decl_type) { return Err(TypeError::new("expected declaration expression".into(), Some(op.clone()))) }
                             
-                            let mut decl_slice = [0; 22];
+                            let mut decl_slice = [0; DECL_BYTES];
                             fill_slice_with_vec(&mut decl_slice, decl_bytes);
                             let decl = Decl::from_bytes(decl_slice, env)
                                 .ok_or(TypeError::new("cannot get declaration name from stack".into(), Some(op.clone())))?;
+                            let mut type_var = TypeVar::new(&decl.name, &decl.dtype, declared_at);
+                            let val_span = right.span();
+                            type_var.constrain("value", &right.check(env)?, val_span)?;
+                            let dtype = type_var.resolve();
                             let name  = decl.name;
-                            let dtype = decl.dtype.union(&right.check(env)?)
-                                .ok_or(TypeError::new("value does not match declaration".into(), None))?;
-                            let dtype_clone = dtype.clone();
-                            let constructor = move |self_expr: Option<Box<Expr>>, _env: &Environment, _arg: Option<Box<Expr>>|
-                            { Expr::Asm(
-                                Box::new(Expr::Object(vec![])),
-                                Box::new(dtype_clone.to_expr()),
-                                Box::new(Expr::Literal(Literal::String(format!(
-                                    " lea jreg#stack_offset, [rbp+ j# {} +{}]; jret# addr( jreg#stack_offset ) ",
-                                    self_expr.unwrap().to_syntax(), size+dtype_clone.size
-                                ))))
-                            )};
+                            msgs.extend(fields::field_msgs(name, dtype.clone(), size));
                             size += dtype.size;
-                            msgs.push(Msg::new(name, Rc::new(constructor), dtype.clone(), None));
                         }
                         _ => {
                             size += expr.check(env)?.size;
@@ -158,56 +218,99 @@ impl TypeCheck for Expr {
                 }
                 Ok(DType::new(size, msgs, false, true))
             },
-            Expr::CodeBlock(exprs) => {
+            Expr::CodeBlock(exprs, _) => {
                 let mut last_type = DType::new(0, vec![], false, true);
                 for expr in exprs {
                     last_type = expr.check(env)?;
                 }
                 Ok(last_type)
             },
-            Expr::Fn(capture_list, expr) => { // TODO: add unnamed captures
-                let mut new_env = Environment::new();
+            Expr::Fn(capture_list, expr, span) => { // TODO: add unnamed captures
+                let mut new_env = Environment::for_fn(env);
+                let mut params = vec![];
+                let mut params_size = 0;
+                // runtime values are captured in a record, which makes the fn a closure
+                let mut captured = vec![];
                 // add capture list to new environment
-                for expr in capture_list {
+                for expr in capture_list.iter_mut() {
                     match expr.clone() {
-                        Expr::MsgEmission(_, msg_name, arg_opt) => {
-                            match arg_opt {
-                                Some(_) => {
+                        // a decl without a value is a param
+                        Expr::BinaryOpt(_, Token { ttype: TokenType::Semicolon, .. }, _) => {
+                            let span = expr.span();
+                            let decl = Decl::from_expr(expr, env)
+                                .ok_or(TypeError::at("could not form declaration".into(), span))?;
+                            if decl.dtype.size_unknown {
+                                return Err(TypeError::at(format!("param {} needs a type of known size", decl.name), span))
+                            }
+                            new_env.add_rt_msg(fields::param_msg(decl.name.clone(), decl.dtype.clone(), params_size));
+                            new_env.add_rt_addr(decl.name.clone(), fields::param_addr(params_size));
+                            params_size += fields::slot_size(&decl.dtype);
+                            params.push(Param::new(decl.name, decl.dtype));
+                        },
+                        // `&: x` captures x by reference
+                        Expr::MsgEmission(None, msg_name, mut args) if msg_name.lexeme == "&" && args.len() == 1 => {
+                            let name = match &args[0] {
+                                Expr::MsgEmission(None, name, inner) if inner.is_empty() => name.lexeme.clone(),
+                                _ => return Err(TypeError::at("expected a name to capture by reference".into(), args[0].span())),
+                            };
+                            let addr = env.get_rt_addr(&name)
+                                .ok_or(TypeError::at(format!("{} is not a runtime value, so it can't be captured by reference", name), args[0].span()))?;
+                            let dtype = args[0].check(env)?;
+                            captured.push((name, dtype, fields::Captured::Ref(addr)));
+                        },
+                        Expr::MsgEmission(_, msg_name, args) => {
+                            match args.is_empty() {
+                                false => {
                                     let _dtype = expr.check(env)?;
                                     return Err(TypeError::new("unnamed captures not supported yet. put your value in an assignment".into(), Some(msg_name)))
                                 },
-                                None => {
-                                    let (bytes, dtype) = expr.interpret(env)
-                                        .ok_or(TypeError::new("expected static expression".into(), Some(msg_name.clone())))?;
-                                    let byte_lits: Vec<Expr> = bytes.iter().map(|b| Expr::Literal(Literal::Byte(*b))).collect();
-                                    let constructor = move |_: Option<Box<Expr>>, _: &Environment, _: Option<Box<Expr>>|
-                                        { Expr::Object(byte_lits.clone()) };
-                                    let msg = Msg::new(msg_name.lexeme, Rc::new(constructor), dtype, None);
-                                    new_env.add_rt_msg(msg.clone());
-                                    new_env.add_ct_msg(msg);
-                                    new_env.push(bytes);
+                                true => match (static_capture(expr, env)?, module_decl(expr, &msg_name.lexeme, env)) {
+                                    (Some((bytes, dtype)), _) => {
+                                        let byte_lits: Vec<Expr> = bytes.iter().map(|b| Expr::Literal(Literal::Byte(*b), Span::default())).collect();
+                                        let constructor = move |_: Option<Box<Expr>>, _: &Environment, _: Vec<Expr>|
+                                            { Expr::Object(byte_lits.clone(), Span::default()) };
+                                        let msg = Msg::new(msg_name.lexeme, Rc::new(constructor), dtype, vec![]);
+                                        new_env.add_rt_msg(msg.clone());
+                                        new_env.add_ct_msg(msg);
+                                        new_env.push(bytes);
+                                    },
+                                    (None, Some(msg)) => new_env.add_rt_msg(msg),
+                                    (None, None) if env.in_fn() => {
+                                        let mut val = expr.clone();
+                                        let dtype = val.check(env)?;
+                                        captured.push((msg_name.lexeme, dtype, fields::Captured::Value(val)));
+                                    },
+                                    (None, None) => return Err(TypeError::new("expected static expression".into(), Some(msg_name))),
                                 },
                             }
                         },
-                        Expr::Binary(mut left, Token{ ttype: TokenType::Equal, lexeme, line, start }, mut right) => {
-                            let tkn_opt = Some(Token::new(TokenType::Equal, lexeme, line, start));
-                            let msg_name = if let Expr::BinaryOpt(_, Token{ ttype: TokenType::Semicolon, lexeme, line, start }, _) = *left.clone() {
-                                let tkn_opt = Some(Token::new(TokenType::Semicolon, lexeme, line, start));
-                                let decl = Decl::from_expr(&mut *left, env)
+                        Expr::Binary(mut left, op @ Token{ ttype: TokenType::Equal, .. }, mut right) => {
+                            let tkn_opt = Some(op);
+                            let msg_name = if let Expr::BinaryOpt(_, op @ Token{ ttype: TokenType::Semicolon, .. }, _) = *left.clone() {
+                                let tkn_opt = Some(op);
+                                let decl = Decl::from_expr(&mut left, env)
                                     .ok_or(TypeError::new("could not form declaration".into(), tkn_opt))?;
                                 decl.name
                             }
                             else { return Err(TypeError::new("expected declaration".into(), tkn_opt)) };
 
-                            let (bytes, dtype) = right.interpret(env) // TODO: make expr be mutated here
-                                .ok_or(TypeError::new("expected static expression".into(), tkn_opt))?;
-                            let byte_lits: Vec<Expr> = bytes.iter().map(|b| Expr::Literal(Literal::Byte(*b))).collect();
-                            let constructor = move |_: Option<Box<Expr>>, _: &Environment, _: Option<Box<Expr>>|
-                                { Expr::Object(byte_lits.clone()) };
-                            let msg = Msg::new(msg_name, Rc::new(constructor), dtype, None);
-                            new_env.add_rt_msg(msg.clone());
-                            new_env.add_ct_msg(msg);
-                            new_env.push(bytes);
+                            match (static_capture(&mut right, env)?, module_decl(&right, &msg_name, env)) { // TODO: make expr be mutated here
+                                (Some((bytes, dtype)), _) => {
+                                    let byte_lits: Vec<Expr> = bytes.iter().map(|b| Expr::Literal(Literal::Byte(*b), Span::default())).collect();
+                                    let constructor = move |_: Option<Box<Expr>>, _: &Environment, _: Vec<Expr>|
+                                        { Expr::Object(byte_lits.clone(), Span::default()) };
+                                    let msg = Msg::new(msg_name, Rc::new(constructor), dtype, vec![]);
+                                    new_env.add_rt_msg(msg.clone());
+                                    new_env.add_ct_msg(msg);
+                                    new_env.push(bytes);
+                                },
+                                (None, Some(msg)) => new_env.add_rt_msg(msg),
+                                (None, None) if env.in_fn() => {
+                                    let dtype = right.check(env)?;
+                                    captured.push((msg_name, dtype, fields::Captured::Value(*right)));
+                                },
+                                (None, None) => return Err(TypeError::new("expected static expression".into(), tkn_opt)),
+                            }
                         },
                         _ => {
                             // let (val, dtype) = expr.interpret(&mut new_env)
@@ -219,30 +322,65 @@ impl TypeCheck for Expr {
                             // new_env.add_rt_size(dtype.size);
                             // new_env.add_ct_size(dtype.size);
                             // new_env.push(val);
-                            return Err(TypeError::new("unnamed captures not supported yet. put your value in an assignment".into(), None))
+                            return Err(TypeError::at("unnamed captures not supported yet. put your value in an assignment".into(), expr.span()))
                         }
                     }
                 }
-                expr.check(&mut new_env)?;
-                Ok(FN)
+                // the record's address comes after the params, so they have to be known first
+                let mut record_size = 0;
+                for (name, dtype, capture) in captured.iter() {
+                    let (msg, addr) = capture.msg(name.clone(), dtype.clone(), params_size, record_size);
+                    new_env.add_rt_msg(msg);
+                    new_env.add_rt_addr(name.clone(), addr);
+                    record_size += capture.size(dtype);
+                }
+
+                let ret_type = expr.check(&mut new_env)?;
+                if fields::holds_closure(&ret_type) {
+                    return Err(TypeError::at("a fn can't return a closure".into(), expr.span()))
+                }
+                // what the fn captured is in its body now, only the params are left to write out
+                capture_list.retain(|expr| matches!(expr, Expr::BinaryOpt(_, Token { ttype: TokenType::Semicolon, .. }, _)));
+                let frame_size = new_env.get_rt_stack_type().size;
+                if frame_size > 0 {
+                    let mut reserve = fields::reserve(frame_size);
+                    reserve.stamp(*span);
+                    **expr = Expr::CodeBlock(vec![reserve, *expr.clone()], *span);
+                }
+                if captured.is_empty() { return Ok(fn_type(params, ret_type)) }
+
+                // the record is kept in the frame of the fn making the closure, under the 16 bytes of
+                // the closure itself. neither is pushed, so making a closure in a loop takes no stack
+                env.add_rt_size(16 + record_size);
+                let record = env.get_rt_stack_type().size;
+                let value = record - record_size;
+                let mut stores = String::new();
+                let mut slot = 0;
+                for (_, dtype, capture) in captured.iter() {
+                    stores.push_str(&capture.store(dtype, record, slot));
+                    slot += capture.size(dtype);
+                }
+                let dtype = closure_type(params, ret_type);
+                // what it captured is already in the fn, so it's made without its capture list
+                let code = Expr::Fn(vec![], expr.clone(), *span);
+                let mut closure = fields::closure_node(&dtype, record, value, stores, &code);
+                closure.stamp(*span);
+                *self = closure;
+                Ok(dtype)
             },
-            Expr::Type(exprs) => {
+            Expr::Type(exprs, _) => {
                 for expr in exprs {
                     let dtype = expr.check(env)?;
-                    if dtype != TYPE
-                    && dtype != DECL
-                        { return Err(TypeError::new("unexpected expression in type definition".into(), None)) }
+                    if !TYPE.is_assignable_from(&dtype)
+                    && !DECL.is_assignable_from(&dtype)
+                        { return Err(TypeError::at("unexpected expression in type definition".into(), expr.span())) }
                 }
                 Ok(TYPE)
             },
-            Expr::Literal(inner) => Ok(DType::from_literal(inner.clone())),
+            Expr::Literal(inner, _) => Ok(DType::from_literal(inner.clone())),
         }
     }
 
-    fn check_new_env(&mut self) -> Result<DType, TypeError> {
-        self.check(&mut Environment::new())
-    }
-
     fn to_syntax(&self) -> String {
         match self {
             Expr::Binary(left, op, right) => {
@@ -253,79 +391,217 @@ impl TypeCheck for Expr {
             },
             Expr::MsgEmission(_, _, _) => panic!("unexpected msg emission in checked ast: {:?}", self),
             Expr::BinaryOpt(left, op, right_opt) => {
-                let mut str = left.to_syntax();
+                // the name of a decl is left as it was written
+                let mut str = match left.as_ref() {
+                    Expr::MsgEmission(None, name, args) if args.is_empty() => name.lexeme.clone(),
+                    _ => left.to_syntax(),
+                };
                 str.push_str(&op.lexeme);
                 if let Some(right) = right_opt {
                     str.push_str(right.to_syntax().as_str());
                 }
                 str
             },
-            Expr::Asm(asm_type, ret_type, text) => {
+            Expr::Asm(asm_type, ret_type, text, _) => {
                 let mut str = "asm ".to_string();
                 str.push_str(asm_type.to_syntax().as_str());
                 str.push_str(ret_type.to_syntax().as_str());
                 str.push_str(text.to_syntax().as_str());
                 str
             },
-            Expr::Object(exprs) => {
+            Expr::Import(path, _) => format!("import: \"{}\"", path.escape_debug()),
+            Expr::Object(exprs, _) => {
                 let mut str = "[ ".to_string();
 
                 for expr in exprs {
                     str.push_str(expr.to_syntax().as_str());
-                    str.push_str(" ");
+                    str.push(' ');
                 }
 
                 str.push(']');
                 str
             },
-            Expr::Fn(exprs, expr) => {
+            Expr::Fn(exprs, expr, _) => {
                 let mut str = "|".to_string();
                 for expr in exprs {
                     str.push_str(expr.to_syntax().as_str());
-                    str.push_str(" ");
+                    str.push(' ');
                 }
                 str.push('|');
                 str.push_str(expr.to_syntax().as_str());
                 str
             },
-            Expr::CodeBlock(exprs) => {
+            Expr::CodeBlock(exprs, _) => {
                 let mut str = "{ ".to_string();
 
                 for expr in exprs {
                     str.push_str(expr.to_syntax().as_str());
-                    str.push_str(" ");
+                    str.push(' ');
                 }
 
                 str.push('}');
                 str
             },
-            Expr::Type(exprs) => {
+            Expr::Type(exprs, _) => {
                 let mut str = "( ".to_string();
 
                 for expr in exprs {
                     str.push_str(expr.to_syntax().as_str());
-                    str.push_str(" ");
+                    str.push(' ');
                 }
 
                 str.push(')');
                 str
             },
-            Expr::Literal(inner) => inner.prettify(),
+            Expr::Literal(inner, _) => inner.prettify(),
         }
     }
 }
 
+// a loop's marker names where its continues and breaks go, and they can't go anywhere outside one
+fn check_asm_loops(text: &str, outside: impl Fn(usize) -> bool, at: impl Fn(usize) -> Span, in_loop: bool) -> Result<(), TypeError> {
+    let mut loop_start = None;
+    for (i, _) in text.match_indices("jloop#").filter(|(i, _)| outside(*i)) {
+        let line_end = text[i..].find('\n').map_or(text.len(), |n| i + n);
+        if text[(i+6)..line_end].split_whitespace().count() != 2 {
+            return Err(TypeError::at("expected continue and break labels after jloop#".into(), at(i)))
+        }
+        loop_start = loop_start.or(Some(i));
+    }
+    let jumps = text.match_indices("jbreak#").chain(text.match_indices("jcontinue#"));
+    for (i, _) in jumps.filter(|(i, _)| outside(*i)) {
+        if !in_loop && loop_start.is_none_or(|start| i < start) {
+            return Err(TypeError::at("break and continue can only be used in a loop".into(), at(i)))
+        }
+    }
+    Ok(())
+}
+
+// the name an expr is, if it's only a name
+fn declared_name(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::MsgEmission(None, name, args) if args.is_empty() => Some(name.lexeme.clone()),
+        _ => None,
+    }
+}
+
+// code a msg runs. a fn is run by calling it, the same as `.!`
+fn check_code(code: &mut Expr, env: &mut Environment) -> Result<DType, TypeError> {
+    let dtype = code.check(env)?;
+    if !matches!(dtype.intrinsic, Some(Intrinsic::Fn) | Some(Intrinsic::Closure)) { return Ok(dtype) }
+    let call = Token::new(TokenType::Identifier, "!".into(), code.span());
+    *code = Expr::MsgEmission(Some(Box::new(code.clone())), call, vec![]);
+    code.check(env)
+}
+
+// the code generator gives each register alias in asm one of four registers, and each sse alias
+// one of eight, so asm can't name more. a jreg without an alias is one more
+fn check_asm_regs(text: &str, outside: impl Fn(usize) -> bool, at: impl Fn(usize) -> Span) -> Result<(), TypeError> {
+    let word = |text: &str| text[..text.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(text.len())].to_string();
+    let alias = |text: &str| match text.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => word(text),
+        _ => String::new(),
+    };
+    let mut regs = vec![];
+    for (i, _) in text.match_indices("jreg").filter(|(i, _)| outside(*i)) {
+        if !["1#", "2#", "4#", "8#"].iter().any(|size| text[(i+4)..].starts_with(size)) {
+            return Err(TypeError::at("expected a register size of 1, 2, 4 or 8 after jreg".into(), at(i)))
+        }
+        let reg = alias(&text[(i+6)..]);
+        if !regs.contains(&reg) { regs.push(reg) }
+        if regs.len() > 4 { return Err(TypeError::at("asm can only use 4 registers".into(), at(i))) }
+    }
+    let mut xmm_regs = vec![];
+    for (i, _) in text.match_indices("jxmm#").filter(|(i, _)| outside(*i)) {
+        let reg = word(&text[(i+5)..]);
+        if !xmm_regs.contains(&reg) { xmm_regs.push(reg) }
+        if xmm_regs.len() > 8 { return Err(TypeError::at("asm can only use 8 sse registers".into(), at(i))) }
+    }
+    Ok(())
+}
+
+// the value of a capture if it's static. asm has no static value yet, so it's only static if its
+// value is of the type it checks as
+fn static_capture(expr: &mut Expr, env: &mut Environment) -> Result<Option<(Vec<u8>, DType)>, TypeError> {
+    let dtype = expr.clone().check(env)?;
+    let mut val = expr.clone();
+    match val.interpret(env) {
+        Some((bytes, ct_dtype)) if dtype.is_assignable_from(&ct_dtype) => {
+            *expr = val;
+            Ok(Some((bytes, ct_dtype)))
+        },
+        _ => Ok(None),
+    }
+}
+
+// the msg of the module's runtime declaration expr names, as name. it's the same in every fn, so
+// it's had without capturing anything
+fn module_decl(expr: &Expr, name: &str, env: &Environment) -> Option<Msg> {
+    match expr {
+        Expr::MsgEmission(None, decl_name, args) if args.is_empty() => {
+            let mut msg = env.get_module_decl(&decl_name.lexeme)?;
+            msg.name = name.to_string();
+            Some(msg)
+        },
+        _ => None,
+    }
+}
+
+// rather than as an object on the stack, which would only give the asm its address
+fn inline_static(expr: &mut Expr, dtype: &DType, env: &mut Environment) {
+    if let Expr::Literal(_, _) = expr { return }
+    let span = expr.span();
+    if let Some(lit) = expr.interpret(env).and_then(|(bytes, _)| literal_from_bytes(dtype, &bytes)) {
+        *expr = Expr::Literal(lit, span);
+    }
+}
+
+impl Module {
+    // declarations are checked in order, each one adding its msg to env
+    pub fn check(&mut self, env: &mut Environment) -> Result<(), TypeError> {
+        for (i, decl) in self.decls.iter().enumerate() {
+            if self.decls[..i].iter().any(|prev| prev.name.lexeme == decl.name.lexeme) {
+                return Err(TypeError::new(format!("{} is already declared in this module", decl.name.lexeme), Some(decl.name.clone())))
+            }
+        }
+        for decl in self.decls.iter_mut() {
+            decl.expr.check(env)?;
+        }
+
+        Ok(())
+    }
+    // only the entry module needs a main
+    pub fn check_entry(&self) -> Result<(), TypeError> {
+        let entry = self.get_decl(Module::ENTRY)
+            .ok_or(TypeError::at(format!("module has no {} entry point", Module::ENTRY), self.span))?;
+        match entry.value() {
+            Expr::Fn(_, _, _) => Ok(()),
+            value => Err(TypeError::at(format!("{} must be initialized with a function", Module::ENTRY), value.span())),
+        }
+    }
+    // the type of an import of this module: a msg for each `pub:` declaration, taken from
+    // the stack type of the env the module was checked in
+    pub fn export_type(&self, stack_type: DType) -> DType {
+        let msgs = self.decls.iter().filter(|decl| decl.public).filter_map(|decl|
+            // the module's own declaration, not anything from the core lib it shadows
+            stack_type.msgs.iter().rev().find(|msg| msg.name == decl.name.lexeme).cloned()
+        ).collect();
+        DType::new(0, msgs, false, false)
+    }
+}
+
 pub struct TypeError {
     msg: String,
-    tkn_opt: Option<Token>
+    span_opt: Option<Span>
 }
 impl TypeError {
-    pub fn new(msg: String, tkn_opt: Option<Token>) -> Self { Self { msg, tkn_opt } }
+    pub fn new(msg: String, tkn_opt: Option<Token>) -> Self { Self { msg, span_opt: tkn_opt.map(|tkn| tkn.span) } }
+    pub fn at(msg: String, span: Span) -> Self { Self { msg, span_opt: Some(span) } }
 }
 impl Display for TypeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self.tkn_opt {
-            Some(tkn) => write!(f, "err: {} at {}", self.msg, tkn.to_string()),
+        match &self.span_opt {
+            Some(span) => write!(f, "err: {} at {}", self.msg, span),
             None => write!(f, "err: {}", self.msg)
         }
     }
@@ -334,4 +610,109 @@ impl Debug for TypeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         Display::fmt(&self, f)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use crate::{expr::{Expr, test_support::parse}, token::{literal::Literal, span::Span}};
+    use super::{TypeCheck, super::{Environment, core_lib::*, dtype::Msg}};
+
+    #[test]
+    fn constructed_spans() {
+        // a msg that constructs a type definition with a value in it
+        let constructor = |_: Option<Box<Expr>>, _: &Environment, _: Vec<Expr>|
+            Expr::Type(vec![Expr::Literal(Literal::Integer(5), Span::default())], Span::default());
+        let mut env = Environment::new();
+        env.add_rt_msg(Msg::new("bad".into(), Rc::new(constructor), TYPE, vec![]));
+        let err = parse("\n  bad").check(&mut env).unwrap_err();
+        assert_eq!(err.to_string(), "err: unexpected expression in type definition at 2:3");
+    }
+
+    #[test]
+    fn embedded_expr_spans() {
+        // what's embedded in asm is placed where it's written, past escapes and stripped indentation
+        let cases = [
+            ("{asm [] I32 \"\\tmov jreg4#a, 1\\n mov jreg4#a, j# nope\\n jret# val( jreg8#a )\"}", "1:49"),
+            ("{asm [] I32 \"\"\"\n    mov jreg4#a, 1\n        add jreg4#a, j# nope\n    jret# val( jreg8#a )\n    \"\"\"}", "3:25"),
+            ("{asm [] I32 r\"mov jreg4#a, j# nope\n jret# val( jreg8#a )\"}", "1:31"),
+        ];
+        for (source, at) in cases.iter() {
+            let err = parse(source).check(&mut Environment::new()).unwrap_err().to_string();
+            assert!(err.ends_with(&format!("has no msg nope at {}", at)), "{}: {}", source, err);
+        }
+    }
+
+    #[test]
+    fn asm_registers() {
+        let error = |text: &str| parse(&format!("{{asm [] I32 \"{}\"}}", text)).check(&mut Environment::new()).unwrap_err().to_string();
+        // a jreg without an alias is one more register
+        let five = "mov jreg4#a, 1\n mov jreg4#b, 1\n mov jreg4#c, 1\n mov jreg4#, 1\n mov jreg4#e, 1\n jret# val( jreg8#a )";
+        assert_eq!(error(five), "err: asm can only use 4 registers at 5:6");
+        assert_eq!(error("mov jreg3#a, 1\n jret# val( jreg8#a )"), "err: expected a register size of 1, 2, 4 or 8 after jreg at 1:18");
+        let sse: String = (0..9).map(|i| format!("movd jxmm#x{}, jreg4#a\n ", i)).collect();
+        assert_eq!(error(&format!("{}jret# val( jreg8#a )", sse)), "err: asm can only use 8 sse registers at 9:7");
+
+        // the registers of an embedded expression are its own
+        let embedded = "mov jreg4#a, j# {asm [] I32 \\\"mov jreg4#b, 1\\n jret# val( jreg8#b )\\\"}\n \
+            mov jreg4#c, 1\n mov jreg4#d, 1\n mov jreg4#e, 1\n jret# val( jreg8#a )";
+        assert!(parse(&format!("{{asm [] I32 \"{}\"}}", embedded)).check(&mut Environment::new()).is_ok());
+    }
+
+    #[test]
+    fn loop_jumps() {
+        let error = |source: &str| parse(source).check(&mut Environment::new()).err().map(|err| err.to_string());
+        let outside = |at: &str| Some(format!("err: break and continue can only be used in a loop at {}", at));
+        assert_eq!(error("{1\n break}"), outside("2:2"));
+        // a fn's body is only in the loops in it, even if it's run by one
+        assert_eq!(error("{loop: ||{ continue }}"), outside("1:12"));
+        assert_eq!(error("{loop: { f ;= ||{ break }\n f.! }}"), outside("1:19"));
+        assert_eq!(error("{asm [] I32 \"jmp jbreak#\n jloop# jlabel#a jlabel#b\n jret# val( rax )\"}"), outside("1:18"));
+        assert_eq!(
+            error("{asm [] I32 \"jloop# jlabel#a\n jret# val( rax )\"}"),
+            Some("err: expected continue and break labels after jloop# at 1:14".into())
+        );
+
+        assert_eq!(error("{while: true, { if_else: true, { break }, { continue } }}"), None);
+        assert_eq!(error("{loop: { ||{ loop: { break } }\n break }}"), None);
+        assert_eq!(error("{asm [] I32 \"jloop# jlabel#a jlabel#b\n jmp jbreak#\n jret# val( rax )\"}"), None);
+    }
+
+    #[test]
+    fn inferred_from_uses() {
+        let check = |body: &str| {
+            let source = format!("{{Point ;= (x; I32 y; I64)\nPair ;= (a; I64 b; I32)\n||{{\n{}\n}}}}", body);
+            parse(&source).check(&mut Environment::new()).map(|_| ()).map_err(|err| err.to_string())
+        };
+        // an object is only bytes until a use says what type it is
+        assert_eq!(check("f ;= |p; Point|{ p.x }\nq ;= [1 2i64]\nf.!: q\nq.y"), Ok(()));
+        assert_eq!(check("q ;= [1 2i64]\nq.y"), Err("err: object of type 12 bytes has no msg y at 5:3".into()));
+
+        // two uses that need different types are both named
+        let conflict = check("f ;= |p; Point|{ p.x }\ng ;= |p; Pair|{ p.b }\nq ;= [1 2i64]\nf.!: q\ng.!: q");
+        assert_eq!(conflict, Err("err: type of q cannot be inferred: argument p of ! at 7:6 is 12 bytes with msgs x set_x y set_y, \
+            argument p of ! is 12 bytes with msgs a set_a b set_b at 8:6".into()));
+    }
+
+    #[test]
+    fn fn_types() {
+        let check = |body: &str| {
+            let source = format!("{{Add ;= (I32 I32) -> I32\n||{{\nadd; Add = |a; I32 b; I32|{{ a + b }}\n{}\n}}}}", body);
+            parse(&source).check(&mut Environment::new()).map(|_| ()).map_err(|err| err.to_string())
+        };
+        assert_eq!(check("x; I32 = add.!: 1, 2"), Ok(()));
+        // a fn value is only the address of its code, so it can be passed to a fn that calls it
+        assert_eq!(check("twice ;= |f; Add x; I32|{ f.!: x, {f.!: x, x} }\ntwice.!: add, 1"), Ok(()));
+
+        let cases = [
+            ("add.!: 1", "err: expected 2 argument(s) to !, found 1 at 4:5"),
+            ("add.!: 1, 2i64", "err: argument 2 is of incorrect type at 4:11"),
+            ("x; I64 = add.!: 1, 2", "err: type of x cannot be inferred: declared type at 4:4 is I64, value is I32 at 4:10"),
+            ("sub; Add = |a; I64 b; I32|{ b }", "err: type of sub cannot be inferred: declared type at 4:6 is (I32 I32) -> I32, \
+                value is (I64 I32) -> I32 at 4:12"),
+            ("add.!: 1, 2\nadd.x", "err: object of type (I32 I32) -> I32 has no msg x at 5:5"),
+        ];
+        for (body, err) in cases.iter() {
+            assert_eq!(check(body), Err(err.to_string()), "{}", body);
+        }
+    }
+}
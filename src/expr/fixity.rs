@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Assoc {
+    Left,
+    Right,
+}
+
+// precedences range from 0 to 9; higher binds tighter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fixity {
+    pub precedence: u8,
+    pub assoc: Assoc,
+}
+
+impl Fixity {
+    pub fn new(precedence: u8, assoc: Assoc) -> Self {
+        Self { precedence, assoc }
+    }
+}
+
+// precedence and associativity of symbolic messages used as infix operators
+#[derive(Debug, Clone)]
+pub struct FixityTable {
+    fixities: HashMap<String, Fixity>,
+}
+
+impl FixityTable {
+    // operators that haven't been declared bind as tightly as any can
+    pub const UNDECLARED: Fixity = Fixity { precedence: 9, assoc: Assoc::Left };
+
+    pub fn new() -> Self {
+        let mut table = Self { fixities: HashMap::new() };
+        for op in ["**"].iter() { table.declare(op, Fixity::new(8, Assoc::Right)) }
+        for op in ["*", "/", "%"].iter() { table.declare(op, Fixity::new(7, Assoc::Left)) }
+        for op in ["+", "-"].iter() { table.declare(op, Fixity::new(6, Assoc::Left)) }
+        for op in ["<<", ">>"].iter() { table.declare(op, Fixity::new(5, Assoc::Left)) }
+        for op in ["<", "<=", ">", ">="].iter() { table.declare(op, Fixity::new(4, Assoc::Left)) }
+        for op in ["==", "!="].iter() { table.declare(op, Fixity::new(3, Assoc::Left)) }
+        for op in ["&"].iter() { table.declare(op, Fixity::new(2, Assoc::Left)) }
+        // `|` opens and closes capture lists, so it can't be infix and is only sent as `a.|: b`
+        for op in ["^"].iter() { table.declare(op, Fixity::new(1, Assoc::Left)) }
+        for op in ["+=", "-=", "*=", "/=", "%=", "_="].iter() { table.declare(op, Fixity::new(0, Assoc::Right)) }
+        table
+    }
+
+    pub fn declare(&mut self, op: &str, fixity: Fixity) {
+        self.fixities.insert(op.to_string(), fixity);
+    }
+    pub fn get(&self, op: &str) -> Fixity {
+        self.fixities.get(op).copied().unwrap_or(Self::UNDECLARED)
+    }
+
+    // identifiers made of symbol characters are operators, e.g. `+`, `!=` or `_=`
+    pub fn is_operator(name: &str) -> bool {
+        !name.is_empty() && !name.chars().any(|c| c.is_alphanumeric())
+    }
+}
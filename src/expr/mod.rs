@@ -1,18 +1,93 @@
 pub mod compiler;
+pub mod fixity;
+pub mod module;
 pub mod parser;
-pub mod checked_expr;
+#[cfg(test)]
+pub mod test_support;
 
-use super::token::{Token, literal::Literal};
+use super::token::{Token, literal::Literal, span::Span};
 
+// variants without a token of their own carry the span of their source text
 #[derive(Clone, Debug)]
 pub enum Expr {
     Binary(Box<Expr>, Token, Box<Expr>),
-    MsgEmission(Option<Box<Expr>>, Token, Option<Box<Expr>>),
+    MsgEmission(Option<Box<Expr>>, Token, Vec<Expr>),
     BinaryOpt(Box<Expr>, Token, Option<Box<Expr>>),
-    Asm(Box<Expr>, Box<Expr>, Box<Expr>),
-    Object(Vec<Expr>),
-    Fn(Vec<Expr>, Box<Expr>),
-    CodeBlock(Vec<Expr>),
-    Type(Vec<Expr>),
-    Literal(Literal),
+    Asm(Box<Expr>, Box<Expr>, Box<Expr>, Span),
+    // path as written, relative to the importing file
+    Import(String, Span),
+    Object(Vec<Expr>, Span),
+    Fn(Vec<Expr>, Box<Expr>, Span),
+    CodeBlock(Vec<Expr>, Span),
+    Type(Vec<Expr>, Span),
+    Literal(Literal, Span),
+}
+
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Binary(left, _, right) => left.span().to(&right.span()),
+            Expr::MsgEmission(self_opt, msg_name, args) => {
+                let start = match self_opt {
+                    Some(self_expr) => self_expr.span(),
+                    None => msg_name.span,
+                };
+                match args.last() {
+                    Some(arg) => start.to(&arg.span()),
+                    None => start.to(&msg_name.span),
+                }
+            },
+            Expr::BinaryOpt(left, op, right_opt) => match right_opt {
+                Some(right) => left.span().to(&right.span()),
+                None => left.span().to(&op.span),
+            },
+            Expr::Asm(_, _, _, span)
+            | Expr::Import(_, span)
+            | Expr::Object(_, span)
+            | Expr::Fn(_, _, span)
+            | Expr::CodeBlock(_, span)
+            | Expr::Type(_, span)
+            | Expr::Literal(_, span) => *span,
+        }
+    }
+    // gives span to everything in the expr that has none of its own, like what a msg constructs.
+    // constructed asm text keeps none, so what's embedded in it isn't placed as though it were
+    // written out in the source
+    pub fn stamp(&mut self, span: Span) {
+        let unset = |own: &mut Span| if *own == Span::default() { *own = span };
+        match self {
+            Expr::Binary(left, op, right) => {
+                unset(&mut op.span);
+                left.stamp(span);
+                right.stamp(span);
+            },
+            Expr::MsgEmission(self_opt, msg_name, args) => {
+                unset(&mut msg_name.span);
+                if let Some(self_expr) = self_opt { self_expr.stamp(span) }
+                for arg in args { arg.stamp(span) }
+            },
+            Expr::BinaryOpt(left, op, right_opt) => {
+                unset(&mut op.span);
+                left.stamp(span);
+                if let Some(right) = right_opt { right.stamp(span) }
+            },
+            Expr::Asm(asm_type, ret_type, _, own) => {
+                unset(own);
+                asm_type.stamp(span);
+                ret_type.stamp(span);
+            },
+            Expr::Fn(exprs, body, own) => {
+                unset(own);
+                for expr in exprs { expr.stamp(span) }
+                body.stamp(span);
+            },
+            Expr::Object(exprs, own)
+            | Expr::CodeBlock(exprs, own)
+            | Expr::Type(exprs, own) => {
+                unset(own);
+                for expr in exprs { expr.stamp(span) }
+            },
+            Expr::Import(_, own) | Expr::Literal(_, own) => unset(own),
+        }
+    }
 }
\ No newline at end of file
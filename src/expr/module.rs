@@ -0,0 +1,85 @@
+use super::Expr;
+use crate::token::{Token, span::Span};
+
+// a compilation unit: the top-level declarations of one source file
+#[derive(Clone, Debug)]
+pub struct Module {
+    pub decls: Vec<ModuleDecl>,
+    pub span: Span,
+}
+
+impl Module {
+    pub const ENTRY: &'static str = "main";
+
+    pub fn new(decls: Vec<ModuleDecl>, span: Span) -> Self {
+        Self { decls, span }
+    }
+
+    pub fn get_decl(&self, name: &str) -> Option<&ModuleDecl> {
+        self.decls.iter().find(|decl| decl.name.lexeme == name)
+    }
+    // paths of every `import:` in the module, in source order
+    pub fn imports(&self) -> Vec<(String, Span)> {
+        let mut imports = vec![];
+        for decl in &self.decls {
+            collect_imports(&decl.expr, &mut imports);
+        }
+        imports
+    }
+}
+
+fn collect_imports(expr: &Expr, imports: &mut Vec<(String, Span)>) {
+    match expr {
+        Expr::Import(path, span) => imports.push((path.clone(), *span)),
+        Expr::Binary(left, _, right) => {
+            collect_imports(left, imports);
+            collect_imports(right, imports);
+        },
+        Expr::MsgEmission(self_opt, _, args) => {
+            if let Some(self_expr) = self_opt { collect_imports(self_expr, imports) }
+            for arg in args { collect_imports(arg, imports) }
+        },
+        Expr::BinaryOpt(left, _, right_opt) => {
+            collect_imports(left, imports);
+            if let Some(right) = right_opt { collect_imports(right, imports) }
+        },
+        Expr::Asm(asm_type, ret_type, text, _) => {
+            collect_imports(asm_type, imports);
+            collect_imports(ret_type, imports);
+            collect_imports(text, imports);
+        },
+        Expr::Fn(exprs, body, _) => {
+            for expr in exprs { collect_imports(expr, imports) }
+            collect_imports(body, imports);
+        },
+        Expr::Object(exprs, _)
+        | Expr::CodeBlock(exprs, _)
+        | Expr::Type(exprs, _) => for expr in exprs { collect_imports(expr, imports) },
+        Expr::Literal(_, _) => {},
+    }
+}
+
+// `name ;= value` or `name; Type = value`, optionally prefixed with `pub:`
+#[derive(Clone, Debug)]
+pub struct ModuleDecl {
+    // declared with `pub:`, so other modules can see it
+    pub public: bool,
+    // the doc comments before it
+    pub docs: Vec<Token>,
+    pub name: Token,
+    pub expr: Expr,
+}
+
+impl ModuleDecl {
+    pub fn new(public: bool, docs: Vec<Token>, name: Token, expr: Expr) -> Self {
+        Self { public, docs, name, expr }
+    }
+
+    // the initializer, once the declaration has been parsed
+    pub fn value(&self) -> &Expr {
+        match &self.expr {
+            Expr::Binary(_, _, value) => value,
+            _ => unreachable!("module declarations are always initialized"),
+        }
+    }
+}
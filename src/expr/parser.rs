@@ -1,139 +1,302 @@
-use super::Expr;
-use crate::token::{Token, TokenType};
+use super::{Expr, fixity::{Assoc, Fixity, FixityTable}, module::{Module, ModuleDecl}};
+use crate::token::{Token, TokenType, literal::Literal, span::Span};
 
 pub struct Parser {
     source: Vec<Token>,
-    next: usize
+    next: usize,
+    errors: Vec<ParseError>,
+    fixities: FixityTable
 }
 
 impl Parser {
-    pub fn new(source: Vec<Token>) -> Self {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        // doc comments are trivia: attach them to the token that follows them
+        let mut source = Vec::with_capacity(tokens.len());
+        let mut docs = vec![];
+        for mut token in tokens {
+            if let TokenType::DocComment(_) = token.ttype { docs.push(token) }
+            else {
+                token.trivia.append(&mut docs);
+                source.push(token);
+            }
+        }
+        // the cursor stops at End, so make sure there always is one
+        if !matches!(source.last(), Some(Token { ttype: TokenType::End, .. })) {
+            let span = source.last().map_or(Span::new(0, 0, 0, 1, 1), |last| last.span.shifted(&last.lexeme));
+            source.push(Token::new(TokenType::End, String::new(), span));
+        }
         Self {
             source,
-            next: 0
+            next: 0,
+            errors: vec![],
+            fixities: FixityTable::new()
         }
     }
 
-    pub fn parse(&mut self) -> Expr {
-        match self.expr() {
-            Ok(expr) => expr,
-            Err(err) => match err.tkn.ttype {
-                TokenType::End => panic!("error at end: {}", err.msg),
-                _ => panic!("error at token {}: {}", err.tkn.to_string(), err.msg)
+    // a lone expression. the compiler itself only parses modules and the exprs embedded in asm
+    #[allow(dead_code)]
+    pub fn parse(&mut self) -> Result<Expr, Vec<ParseError>> {
+        let mut decl = Ok(());
+        while decl.is_ok() && self.at_fixity_decl() { decl = self.fixity_decl() }
+        let expr = decl.and_then(|_| self.expr());
+        if expr.is_ok() && self.peak().ttype != TokenType::End {
+            self.errors.push(ParseError::new(self.peak(), "Expected end of input"));
+        }
+        self.finish(expr)
+    }
+    pub fn parse_module(&mut self) -> Result<Module, Vec<ParseError>> {
+        let start = self.peak().span;
+        let mut decls = vec![];
+        while !self.is_at_end() {
+            let from = self.next;
+            let parsed = if self.at_fixity_decl() { self.fixity_decl().map(|_| None) }
+            else { self.module_decl().map(Some) };
+            match parsed {
+                Ok(decl) => decls.extend(decl),
+                Err(err) => {
+                    self.errors.push(err);
+                    self.synchronize_decl(from);
+                }
             }
         }
+        let module = Module::new(decls, start.to(&self.peak().span));
+        if self.errors.is_empty() { Ok(module) } else { Err(std::mem::take(&mut self.errors)) }
     }
     // parse and return last token with the expr
-    pub fn parse_and_last_token(&mut self) -> (Expr, Token) {
-        match self.expr() {
-            Ok(expr) => (expr, self.previous()),
-            Err(err) => match err.tkn.ttype {
-                TokenType::End => panic!("error at end: {}", err.msg),
-                _ => panic!("error at token {}: {}", err.tkn.to_string(), err.msg)
+    pub fn parse_and_last_token(&mut self) -> Result<(Expr, Token), Vec<ParseError>> {
+        let expr = self.expr();
+        let last = self.previous();
+        self.finish(expr).map(|expr| (expr, last))
+    }
+    // errors recovered from inside lists are reported alongside the one that ended the parse
+    fn finish(&mut self, expr: Result<Expr, ParseError>) -> Result<Expr, Vec<ParseError>> {
+        match expr {
+            Ok(expr) if self.errors.is_empty() => Ok(expr),
+            Ok(_) => Err(std::mem::take(&mut self.errors)),
+            Err(err) => {
+                self.errors.push(err);
+                Err(std::mem::take(&mut self.errors))
             }
         }
     }
 
     fn expr(&mut self) -> Result<Expr, ParseError> {
         let mut expr = self.in_expr()?;
-        loop { match self.peak().ttype {
-            TokenType::Equal => expr = self.binary(expr, false)?,
-            _ => break
-        }}
+        while self.peak().ttype == TokenType::Equal {
+            expr = self.binary(expr, false)?
+        }
         Ok(expr)
     }
     fn in_expr(&mut self) -> Result<Expr, ParseError> {
+        self.infix(0)
+    }
+    // `a + b` is sugar for `a.+: b`
+    fn infix(&mut self, min_precedence: u8) -> Result<Expr, ParseError> {
+        let mut expr = self.postfix()?;
+        loop {
+            let op = self.peak();
+            // `+: x` is a message to the stack's self, not an operator
+            if !Self::is_infix(&op) || self.peak_nth(1).ttype == TokenType::Colon { break }
+            let fixity = self.fixities.get(&op.lexeme);
+            if fixity.precedence < min_precedence { break }
+            self.advance();
+
+            let right = match fixity.assoc {
+                Assoc::Left => self.infix(fixity.precedence + 1)?,
+                Assoc::Right => self.infix(fixity.precedence)?,
+            };
+            expr = Expr::MsgEmission(Some(Box::new(expr)), op, vec![right]);
+        }
+        Ok(expr)
+    }
+    fn postfix(&mut self) -> Result<Expr, ParseError> {
         let mut expr = self.literal()?;
         loop { match self.peak().ttype {
             TokenType::Semicolon => expr = self.binary_opt(expr)?,
-            TokenType::RightArrow
-            | TokenType::Carrot => expr = self.binary(expr, true)?,
+            TokenType::RightArrow => expr = self.binary(expr, true)?,
             TokenType::Period => expr = self.msg_emission(expr)?,
             _ => break
         }}
         Ok(expr)
     }
+    // `^` is scanned as a token of its own, but it's an operator like the others
+    fn is_infix(op: &Token) -> bool {
+        op.ttype == TokenType::Identifier && FixityTable::is_operator(&op.lexeme) || op.ttype == TokenType::Carrot
+    }
     fn literal(&mut self) -> Result<Expr, ParseError> {
         let tkn = self.peak();
         match tkn.ttype {
-            TokenType::Literal(inner) => { self.advance(); Ok(Expr::Literal(inner)) },
+            TokenType::Literal(inner) => { self.advance(); Ok(Expr::Literal(inner, tkn.span)) },
             TokenType::Identifier
             | TokenType::Underscore
             | TokenType::Self_ => {
                 self.advance();
-                let mut arg = None;
-
-                if let TokenType::Colon = self.peak().ttype {
-                    self.advance();
-                    arg = Some(Box::new(self.in_expr()?));
-                }
-
-                Ok(Expr::MsgEmission(None, tkn, arg))
+                let args = self.args(Self::in_expr)?;
+                Ok(Expr::MsgEmission(None, tkn, args))
             },
             TokenType::LeftSqBracket => {
                 self.advance();
-                let mut exprs = Vec::new();
-                while self.peak().ttype != TokenType::RightSqBracket {
-                    if let TokenType::End = self.peak().ttype { return Err(ParseError{
-                        tkn: self.peak(),
-                        msg: "Unterminated object literal".to_string()
-                    }) }
-                    exprs.push(self.expr()?);
-                }
-                self.advance();
-                Ok(Expr::Object(exprs))
+                let (exprs, end) = self.list(TokenType::RightSqBracket, "Unterminated object literal")?;
+                Ok(Expr::Object(exprs, tkn.span.to(&end.span)))
             },
             TokenType::Pipe => {
                 self.advance();
-                let mut capture_list = Vec::new();
-                while self.peak().ttype != TokenType::Pipe {
-                    if let TokenType::End = self.peak().ttype { return Err(ParseError{
-                        tkn: self.peak(),
-                        msg: "Unterminated capture list".to_string()
-                    })} else { capture_list.push(self.expr()?); }
-                }
-                self.advance();
+                let (capture_list, _) = self.list(TokenType::Pipe, "Unterminated capture list")?;
 
-                Ok(Expr::Fn(capture_list, Box::new(self.expr()?)))
+                let body = self.expr()?;
+                let span = tkn.span.to(&body.span());
+                Ok(Expr::Fn(capture_list, Box::new(body), span))
             },
             TokenType::LeftBrace => {
                 self.advance();
-                let mut exprs = Vec::new();
-                while self.peak().ttype != TokenType::RightBrace {
-                    if let TokenType::End = self.peak().ttype { return Err(ParseError{
-                        tkn: self.peak(),
-                        msg: "Unterminated code block".to_string()
-                    }) }
-                    exprs.push(self.expr()?);
-                }
-                self.advance();
-                Ok(Expr::CodeBlock(exprs))
+                let (exprs, end) = self.list(TokenType::RightBrace, "Unterminated code block")?;
+                Ok(Expr::CodeBlock(exprs, tkn.span.to(&end.span)))
             }
             TokenType::LeftParen => self.dtype(),
             TokenType::Asm => self.asm(),
-            _ => Err(ParseError{ tkn, msg: "Invalid expression-starting token".to_string() })
+            TokenType::Import => self.import(),
+            TokenType::End => Err(ParseError::new(tkn, "Expected expression, found end of input")),
+            _ => Err(ParseError::new(tkn, "Invalid expression-starting token"))
         }
     }
     fn dtype(&mut self) -> Result<Expr, ParseError> {
-        self.advance();
+        let start = self.advance();
+        let (exprs, end) = self.list(TokenType::RightParen, "Unterminated type definition")?;
+        Ok(Expr::Type(exprs, start.span.to(&end.span)))
+    }
+    // parse exprs up to and including the closing token. an expr that fails to parse is
+    // recorded and skipped, so the rest of the list is still parsed
+    fn list(&mut self, closing: TokenType, unterminated: &str) -> Result<(Vec<Expr>, Token), ParseError> {
         let mut exprs = Vec::new();
-        while self.peak().ttype != TokenType::RightParen {
-            if self.peak().ttype == TokenType::End { return Err(ParseError{
-                tkn: self.peak(),
-                msg: "Unterminated type definition".to_string()
-            }) }
-            exprs.push(self.expr()?);
+        // fixities declared in a list only apply until its end
+        let fixities = self.fixities.clone();
+        while self.peak().ttype != closing {
+            if let TokenType::End = self.peak().ttype { return Err(ParseError::new(self.peak(), unterminated)) }
+            let parsed = if self.at_fixity_decl() { self.fixity_decl().map(|_| None) }
+            else { self.expr().map(Some) };
+            match parsed {
+                Ok(expr) => exprs.extend(expr),
+                Err(err) => {
+                    self.errors.push(err);
+                    self.synchronize(&closing);
+                }
+            }
+        }
+        self.fixities = fixities;
+        Ok((exprs, self.advance()))
+    }
+    // `infixl 6 +` or `infixr 0 +=`, with a precedence from 0 to 9, applying to the
+    // rest of the enclosing list. without a precedence `infixl` is an ordinary identifier
+    fn at_fixity_decl(&self) -> bool {
+        let keyword = self.peak();
+        keyword.ttype == TokenType::Identifier
+        && (keyword.lexeme == "infixl" || keyword.lexeme == "infixr")
+        && matches!(self.peak_nth(1).ttype, TokenType::Literal(Literal::Integer(_)))
+    }
+    fn fixity_decl(&mut self) -> Result<(), ParseError> {
+        let assoc = if self.advance().lexeme == "infixl" { Assoc::Left } else { Assoc::Right };
+        let precedence_tkn = self.advance();
+        let precedence = match precedence_tkn.ttype {
+            TokenType::Literal(Literal::Integer(p)) if (0..=9).contains(&p) => p as u8,
+            _ => return Err(ParseError::new(precedence_tkn, "Operator precedence must be between 0 and 9"))
+        };
+        let op = self.peak();
+        if !Self::is_infix(&op) {
+            return Err(ParseError::new(op, "Expected operator in fixity declaration"))
         }
         self.advance();
-        Ok(Expr::Type(exprs))
+        self.fixities.declare(&op.lexeme, Fixity::new(precedence, assoc));
+        Ok(())
+    }
+    fn module_decl(&mut self) -> Result<ModuleDecl, ParseError> {
+        let public = self.peak().ttype == TokenType::Identifier && self.peak().lexeme == "pub"
+            && self.peak_nth(1).ttype == TokenType::Colon;
+        // docs before `pub:` document the declaration as much as the ones after it
+        let mut docs = vec![];
+        if public {
+            docs = self.advance().trivia;
+            self.advance();
+        }
+        docs.append(&mut self.source[self.next].trivia);
+        let start = self.peak();
+        let expr = self.expr()?;
+        let name = match &expr {
+            Expr::Binary(left, Token { ttype: TokenType::Equal, .. }, _) => match &**left {
+                Expr::BinaryOpt(name, Token { ttype: TokenType::Semicolon, .. }, _) => match &**name {
+                    Expr::MsgEmission(None, name, args) if args.is_empty() => Some(name.clone()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        };
+        match name {
+            Some(name) => Ok(ModuleDecl::new(public, docs, name, expr)),
+            None => Err(ParseError::new(start, "Expected an initialized declaration at the top level of a module")),
+        }
+    }
+    // skip to the start of a line outside of any group, where the next declaration should begin.
+    // from is where the failed declaration started
+    fn synchronize_decl(&mut self, from: usize) {
+        let at_line_start = |parser: &Self| parser.previous().span.line < parser.peak().span.line;
+        if self.next > from && at_line_start(self) { return }
+        let mut depth = 0usize;
+        loop {
+            match self.advance().ttype {
+                TokenType::LeftSqBracket
+                | TokenType::LeftBrace
+                | TokenType::LeftParen => depth += 1,
+                TokenType::RightSqBracket
+                | TokenType::RightBrace
+                | TokenType::RightParen => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            if self.is_at_end() || (depth == 0 && at_line_start(self)) { return }
+        }
+    }
+    // skip to the closing token of the list being parsed, stepping over nested groups.
+    // closing tokens that don't belong to anything are skipped too
+    fn synchronize(&mut self, closing: &TokenType) {
+        let mut open = vec![];
+        loop {
+            let ttype = self.peak().ttype;
+            match ttype {
+                TokenType::End => return,
+                TokenType::LeftSqBracket => open.push(TokenType::RightSqBracket),
+                TokenType::LeftBrace => open.push(TokenType::RightBrace),
+                TokenType::LeftParen => open.push(TokenType::RightParen),
+                _ if open.last() == Some(&ttype) => { open.pop(); },
+                _ if &ttype == closing => return,
+                _ => {}
+            }
+            self.advance();
+        }
     }
     fn asm(&mut self) -> Result<Expr, ParseError> {
-        self.advance();
+        let start = self.advance();
         let asm_type = self.in_expr()?;
         let ret_type = self.in_expr()?;
         let asm_code = self.in_expr()?;
-
-        Ok(Expr::Asm(Box::new(asm_type), Box::new(ret_type), Box::new(asm_code)))
+        let span = start.span.to(&asm_code.span());
+        Ok(Expr::Asm(Box::new(asm_type), Box::new(ret_type), Box::new(asm_code), span))
+    }
+    // `import: "path"`
+    fn import(&mut self) -> Result<Expr, ParseError> {
+        let start = self.advance();
+        if self.peak().ttype != TokenType::Colon {
+            return Err(ParseError::new(self.peak(), "Expected ':' after import"))
+        }
+        self.advance();
+        match self.peak().ttype {
+            TokenType::Literal(Literal::String(path, _)) => match String::from_utf8(path) {
+                Ok(path) => {
+                    let end = self.advance();
+                    Ok(Expr::Import(path, start.span.to(&end.span)))
+                },
+                Err(_) => Err(ParseError::new(self.peak(), "Import path must be valid UTF-8")),
+            },
+            _ => Err(ParseError::new(self.peak(), "Expected a path string after 'import:'"))
+        }
     }
     fn binary_opt(&mut self, left: Expr) -> Result<Expr, ParseError> {
         let op = self.advance();
@@ -163,36 +326,52 @@ impl Parser {
         Ok(Expr::BinaryOpt(Box::new(left), op, right))
     }
     fn binary(&mut self, left: Expr, in_expr: bool) -> Result<Expr, ParseError> {
-        self.advance();
-        let expr;
-        if in_expr { expr = Expr::Binary(Box::new(left), self.previous(), Box::new(self.in_expr()?)) }
-        else { expr = Expr::Binary(Box::new(left), self.previous(), Box::new(self.expr()?)) }
-        Ok(expr) // TODO: remove use of expr variable
+        let op = self.advance();
+        let right = if in_expr { self.in_expr()? } else { self.expr()? };
+        Ok(Expr::Binary(Box::new(left), op, Box::new(right)))
     }
     fn msg_emission(&mut self, left: Expr) -> Result<Expr, ParseError> {
         self.advance();
-        let msg_name = self.advance();
-        let mut arg = None;
-
+        let msg_name = match self.peak().ttype {
+            TokenType::Identifier
+            | TokenType::Underscore
+            | TokenType::Self_ => self.advance(),
+            // reserved symbols that are still ordinary msg names, like `x.|: y`
+            TokenType::Pipe
+            | TokenType::Carrot => self.advance(),
+            TokenType::End => return Err(ParseError::new(self.peak(), "Expected message name, found end of input")),
+            _ => return Err(ParseError::new(self.peak(), "Expected message name after '.'"))
+        };
+        let args = self.args(Self::literal)?;
+        Ok( Expr::MsgEmission(Some(Box::new(left)), msg_name, args) )
+    }
+    // `: a, b, c` after a msg name, each arg parsed by parse_arg
+    fn args(&mut self, parse_arg: fn(&mut Self) -> Result<Expr, ParseError>) -> Result<Vec<Expr>, ParseError> {
+        let mut args = vec![];
         if let TokenType::Colon = self.peak().ttype {
             self.advance();
-            arg = Some(Box::new(self.literal()?));
+            args.push(parse_arg(self)?);
+            while let TokenType::Comma = self.peak().ttype {
+                self.advance();
+                args.push(parse_arg(self)?);
+            }
         }
-        Ok( Expr::MsgEmission(Some(Box::new(left)), msg_name, arg) )
+        Ok(args)
     }
 
     fn is_at_end(&self) -> bool {
-        match self.peak().ttype {
-            TokenType::End => {}
-            _ => {}
-        }
-        false
+        self.peak().ttype == TokenType::End
     }
     fn peak(&self) -> Token {
         self.source[self.next].clone()
     }
+    // never looks past End
+    fn peak_nth(&self, n: usize) -> Token {
+        self.source[(self.next + n).min(self.source.len() - 1)].clone()
+    }
+    // before anything is consumed this is the first token
     fn previous(&self) -> Token {
-        self.source[self.next - 1].clone()
+        self.source[self.next.saturating_sub(1)].clone()
     }
     fn advance(&mut self) -> Token {
         if !self.is_at_end() { self.next += 1; }
@@ -202,6 +381,164 @@ impl Parser {
 
 #[derive(Debug, Clone)]
 pub struct ParseError {
-    tkn: Token,
-    msg: String
-}
\ No newline at end of file
+    pub span: Span,
+    pub msg: String
+}
+
+impl ParseError {
+    fn new(tkn: Token, msg: &str) -> Self {
+        Self { span: tkn.span, msg: msg.to_string() }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::Parser;
+    use crate::{error::Error, expr::test_support::try_parse, pprint::PPrint, scanner::Scanner, token::{Token, TokenType}};
+
+    fn parse_module(source: &str) -> Result<super::Module, Vec<super::ParseError>> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens_err_ignore();
+        Parser::new(tokens).parse_module()
+    }
+
+    // every prefix of a program that cuts off before its last token must be an error
+    fn assert_truncations_err(source: &str) {
+        let end = source.trim_end().len();
+        for (i, _) in source.char_indices().filter(|(i, _)| *i > 0 && *i < end) {
+            assert!(parse_module(&source[..i]).is_err(), "truncated at byte {} parsed:\n{}", i, &source[..i]);
+        }
+    }
+
+    #[test]
+    fn truncated_test_src() {
+        assert_truncations_err(include_str!("../../test_src.jup"));
+    }
+
+    #[test]
+    fn truncated_goal_src() {
+        assert_truncations_err(include_str!("../../goal_src.jup"));
+    }
+
+    #[test]
+    fn truncated_productions() {
+        let corpus = [
+            "", "x.", "x.:", "x:", "x =", "x; I32 =", "[", "[x", "[x.", "{", "{x = ", "(", "(x;",
+            "|", "|x", "|x|", "asm", "asm NASM", "asm NASM I32", "x.y:", "[x ;= |c ;= 1|{ c.", "]", ")", "}",
+        ];
+        for source in corpus.iter() {
+            assert!(try_parse(source).is_err(), "parsed: {}", source);
+        }
+    }
+
+    // operators as s-expressions, e.g. `a + b * c` is `(+ a (* b c))`
+    fn operators(expr: &super::Expr) -> String {
+        match expr {
+            super::Expr::MsgEmission(Some(left), op, args) if args.len() == 1 =>
+                format!("({} {} {})", op.lexeme, operators(left), operators(&args[0])),
+            super::Expr::MsgEmission(None, name, args) if args.is_empty() => name.lexeme.clone(),
+            super::Expr::CodeBlock(exprs, _) => exprs.iter().map(operators).collect::<Vec<_>>().join(" "),
+            super::Expr::Literal(lit, _) => format!("{:?}", lit),
+            _ => "?".to_string(),
+        }
+    }
+
+    #[test]
+    fn infix_operators() {
+        let cases = [
+            ("a + b * c", "(+ a (* b c))"),
+            ("a * b + c", "(+ (* a b) c)"),
+            ("a - b - c", "(- (- a b) c)"),
+            ("a ** b ** c", "(** a (** b c))"),
+            ("a += b + c", "(+= a (+ b c))"),
+            ("a < b == c > d", "(== (< a b) (> c d))"),
+            ("a <*> b + c", "(+ (<*> a b) c)"),
+            ("a < b == false", "(== (< a b) Bool(false))"),
+            ("a << 1b16 + 2b64", "(<< a (+ B16(1) B64(2)))"),
+            ("a ^ b & c == d", "(^ a (& b (== c d)))"),
+            ("{ infixl 7 ^ a ^ b + c }", "(+ (^ a b) c)"),
+            ("a.|: b ^ c", "(^ (| a b) c)"),
+            ("{ infixr 1 <> a <> b <> c }", "(<> a (<> b c))"),
+            ("{ infixl 7 + a + b * c }", "(* (+ a b) c)"),
+            ("{ { infixl 7 + } a + b * c }", " (+ a (* b c))"),
+        ];
+        for (source, expected) in cases.iter() {
+            assert_eq!(&operators(&try_parse(source).unwrap()), expected, "{}", source);
+        }
+        assert!(try_parse("{ infixl 10 + }").is_err());
+        assert!(try_parse("{ infixl 1 a }").is_err());
+        // `|` would start a capture list, so it's only a msg
+        assert!(try_parse("a | b").is_err());
+    }
+
+    #[test]
+    fn multiple_arguments() {
+        let arg_counts = |source: &str| match try_parse(source).unwrap() {
+            super::Expr::MsgEmission(_, _, args) => args.len(),
+            _ => panic!("expected msg emission: {}", source),
+        };
+        assert_eq!(arg_counts("obj.put: key, value"), 2);
+        assert_eq!(arg_counts("put: key + 1, value, [1 2]"), 3);
+        assert_eq!(arg_counts("obj.put: key"), 1);
+        assert_eq!(arg_counts("obj.put"), 0);
+        assert_eq!(arg_counts("a.|: b"), 1);
+        assert_eq!(arg_counts("a.^: b"), 1);
+        assert!(try_parse("obj.put: key,").is_err());
+    }
+
+    #[test]
+    fn modules() {
+        let module = parse_module("x ;= 1\npub: main ;= ||{ x }\ninfixl 6 <>\ny; I32 = 2 <> 3").unwrap();
+        let names: Vec<(&str, bool)> = module.decls.iter().map(|decl| (decl.name.lexeme.as_str(), decl.public)).collect();
+        assert_eq!(names, vec![("x", false), ("main", true), ("y", false)]);
+        assert!(parse_module("").unwrap().decls.is_empty());
+
+        // each bad declaration is reported, and the ones after it are still parsed
+        let errors = parse_module("x\ny ;= )\nz ;= [1 2]\nw; I32").unwrap_err();
+        let lines: Vec<usize> = errors.iter().map(|err| err.span.line).collect();
+        assert_eq!(lines, vec![1, 2, 4]);
+    }
+
+    #[test]
+    fn error_positions() {
+        let printed = |source: &str| -> Vec<String> {
+            parse_module(source).unwrap_err().iter().map(|err| Error::from(err).to_string()).collect()
+        };
+        assert_eq!(printed("x ;= [1 2]
+y ;= ( 1"), vec!["Unterminated type definition at 2:9"]);
+        assert_eq!(printed("x ;= 1
+  y ;= )"), vec!["Invalid expression-starting token at 2:8"]);
+    }
+
+    #[test]
+    fn doc_comments() {
+        let doc_texts = |tokens: &[Token]| -> Vec<String> {
+            tokens.iter().filter_map(|t| match &t.ttype { TokenType::DocComment(text) => Some(text.clone()), _ => None }).collect()
+        };
+        let source = "## x\nx ;= 1\n## main\n## runs\npub: ## after pub\nmain ;= ||{ x }\n";
+        let module = parse_module(source).unwrap();
+        assert_eq!(doc_texts(&module.decls[0].docs), vec![" x"]);
+        assert_eq!(doc_texts(&module.decls[1].docs), vec![" main", " runs", " after pub"]);
+
+        // printing a module keeps its doc comments
+        let scanned_docs = |source: &str| doc_texts(&Scanner::new(source.to_string()).scan_tokens_recover().0);
+        assert_eq!(scanned_docs(&module.prettify()), scanned_docs(source));
+        let prelude = include_str!("compiler/prelude.jup");
+        assert_eq!(scanned_docs(&parse_module(prelude).unwrap().prettify()), scanned_docs(prelude));
+    }
+
+    #[test]
+    fn imports() {
+        let module = parse_module("a ;= import: \"a.jup\"\nmain ;= ||{ b ;= [import: \"lib/b.jup\"] }").unwrap();
+        let paths: Vec<String> = module.imports().into_iter().map(|(path, _)| path).collect();
+        assert_eq!(paths, vec!["a.jup", "lib/b.jup"]);
+        assert!(try_parse("import \"a.jup\"").is_err());
+        assert!(try_parse("import: a").is_err());
+    }
+
+    #[test]
+    fn complete_programs() {
+        assert!(parse_module(include_str!("../../test_src.jup")).is_ok());
+        assert!(parse_module(include_str!("../../goal_src.jup")).is_ok());
+        assert!(try_parse("x; I32 = 1").is_ok());
+        assert!(try_parse("x.y: 1").is_ok());
+    }
+}
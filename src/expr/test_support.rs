@@ -0,0 +1,11 @@
+use crate::scanner::Scanner;
+use super::{Expr, parser::{ParseError, Parser}};
+
+// source parsed as a lone expression, the way the tests give the compiler code. tokens the scanner
+// couldn't make are left out
+pub fn try_parse(source: &str) -> Result<Expr, Vec<ParseError>> {
+    Parser::new(Scanner::new(source.to_string()).scan_tokens_err_ignore()).parse()
+}
+pub fn parse(source: &str) -> Expr {
+    try_parse(source).unwrap()
+}
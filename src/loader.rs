@@ -0,0 +1,118 @@
+use std::{collections::HashMap, fmt::Display, path::{Path, PathBuf}, rc::Rc};
+
+use crate::{
+    error::Error,
+    expr::{compiler::{Environment, TypeError, core_lib}, module::Module, parser::{ParseError, Parser}},
+    scanner::{error::ScannerError, Scanner},
+    token::span::Span,
+};
+
+// loads a module and everything it imports, parsing and checking each file once
+pub struct Loader {
+    // checked modules, each one after the modules it imports
+    modules: Vec<(Module, Environment)>,
+    loaded: HashMap<PathBuf, usize>,
+    // files currently being loaded, outermost first
+    loading: Vec<PathBuf>,
+}
+
+impl Loader {
+    pub fn new() -> Self {
+        Self {
+            modules: vec![],
+            loaded: HashMap::new(),
+            loading: vec![],
+        }
+    }
+
+    // returns the index of the module in into_modules
+    pub fn load(&mut self, path: &Path) -> Result<usize, LoadError> {
+        // every module is checked with the prelude
+        if let Err(e) = core_lib::prelude() { return Err(LoadError::Prelude(e)) }
+        let path = path.canonicalize().map_err(|e| LoadError::IO(path.to_path_buf(), e))?;
+        if let Some(i) = self.loaded.get(&path) { return Ok(*i) }
+        if let Some(i) = self.loading.iter().position(|loading| loading == &path) {
+            let mut cycle = self.loading[i..].to_vec();
+            cycle.push(path);
+            return Err(LoadError::Cycle(cycle))
+        }
+
+        self.loading.push(path.clone());
+        let loaded = self.load_new(&path);
+        self.loading.pop();
+
+        self.modules.push(loaded?);
+        self.loaded.insert(path, self.modules.len() - 1);
+        Ok(self.modules.len() - 1)
+    }
+    fn load_new(&mut self, path: &PathBuf) -> Result<(Module, Environment), LoadError> {
+        let source = std::fs::read_to_string(path).map_err(|e| LoadError::IO(path.clone(), e))?;
+        let (tokens, errors) = Scanner::new(source).scan_tokens_recover();
+        if !errors.is_empty() { return Err(LoadError::Scanner(path.clone(), errors)) }
+        let mut module = Parser::new(tokens).parse_module()
+            .map_err(|errors| LoadError::Parser(path.clone(), errors))?;
+
+        // imports resolve relative to the importing file
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut imports = vec![];
+        for (import, span) in module.imports() {
+            let i = self.load(&dir.join(&import)).map_err(|e| match e {
+                LoadError::IO(_, e) => LoadError::Import(path.clone(), import.clone(), span, e),
+                e => e,
+            })?;
+            let (imported, imported_env) = &self.modules[i];
+            imports.push((import, imported.export_type(imported_env.get_rt_stack_type())));
+        }
+        // the module goes right after everything it imports
+        let mut env = Environment::for_module(self.modules.len());
+        for (import, dtype) in imports {
+            env.add_import(import, dtype);
+        }
+
+        module.check(&mut env).map_err(|e| LoadError::Type(path.clone(), e))?;
+        Ok((module, env))
+    }
+
+    pub fn into_modules(self) -> Vec<(Module, Environment)> {
+        self.modules
+    }
+}
+
+pub enum LoadError {
+    IO(PathBuf, std::io::Error),
+    // importing file, path as written, where it was imported
+    Import(PathBuf, String, Span, std::io::Error),
+    Scanner(PathBuf, Vec<ScannerError>),
+    Parser(PathBuf, Vec<ParseError>),
+    Type(PathBuf, TypeError),
+    // each file imports the next, and the last is the first again
+    Cycle(Vec<PathBuf>),
+    // why the prelude couldn't be loaded, which is the same for every module
+    Prelude(Rc<LoadError>),
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::IO(path, e) => write!(f, "{}: {}", path.display(), e),
+            LoadError::Import(path, import, span, e) =>
+                write!(f, "{}: cannot import {}: {} at {}", path.display(), import, e, span),
+            LoadError::Scanner(path, errors) => {
+                let errors: Vec<String> = errors.iter()
+                    .map(|e| format!("{}: {}", path.display(), Error::from(e.clone()))).collect();
+                write!(f, "{}", errors.join("\n"))
+            },
+            LoadError::Parser(path, errors) => {
+                let errors: Vec<String> = errors.iter()
+                    .map(|e| format!("{}: {}", path.display(), Error::from(e))).collect();
+                write!(f, "{}", errors.join("\n"))
+            },
+            LoadError::Type(path, e) => write!(f, "{}: {}", path.display(), e),
+            LoadError::Cycle(cycle) => {
+                let cycle: Vec<String> = cycle.iter().map(|path| path.display().to_string()).collect();
+                write!(f, "import cycle: {}", cycle.join(" -> "))
+            },
+            LoadError::Prelude(e) => write!(f, "{}", e),
+        }
+    }
+}
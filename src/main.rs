@@ -2,16 +2,14 @@ use expr::compiler::code_generator::{
     asm_type::{AsmLanguage, AsmTarget},
     CodeGenerator,
 };
-use expr::compiler::Environment;
-use expr::compiler::TypeCheck;
-use expr::parser::Parser;
-use scanner::Scanner;
-use std::io::prelude::*;
+use loader::Loader;
 use std::io::Result;
+use std::path::Path;
 
 mod error;
 mod expr;
 mod linker;
+mod loader;
 mod pprint;
 mod scanner;
 mod token;
@@ -29,25 +27,24 @@ fn main() -> Result<()> {
 }
 
 fn parse_file(path: String) -> Result<()> {
-    let mut file = std::fs::File::open(path)?;
-    let mut contents = String::new();
-
-    file.read_to_string(&mut contents).unwrap();
-
-    let mut scanner = Scanner::new(contents);
-    match scanner.scan_tokens() {
-        Ok(tokens) => {
-            let mut parser = Parser::new(tokens);
-            let mut ast = parser.parse();
-            let mut env = Environment::new();
-            ast.check(&mut env).unwrap();
-            // generate code from ast; go back down the mountain
-            let generator = CodeGenerator::new(AsmLanguage::NASM);
-
-            generator.generate_ir(ast, "test.jir".into(), AsmTarget::X86Unix, &mut env);
+    // scan, parse and check the file along with everything it imports
+    let mut loader = Loader::new();
+    if let Err(e) = loader.load(Path::new(&path)) {
+        println!("{}", e);
+        return Ok(());
+    }
+    let modules = loader.into_modules();
+    // the file we were given is loaded last
+    if let Some((module, _)) = modules.last() {
+        if let Err(e) = module.check_entry() {
+            println!("{}: {}", path, e);
+            return Ok(());
         }
-        Err(e) => println!("{e}"),
     }
+    // generate code from ast; go back down the mountain
+    let generator = CodeGenerator::new(AsmLanguage::Nasm);
+
+    generator.generate_ir(modules, "test.jir".into(), AsmTarget::X86Unix);
 
     Ok(())
 }
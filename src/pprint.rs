@@ -1,37 +1,37 @@
-use super::expr::Expr;
-use super::token::literal::Literal;
+use super::expr::{Expr, module::Module};
+use super::token::{literal::Literal, Token, TokenType};
 
 pub trait PPrint {
     fn prettify(&self) -> String;
-    fn pprint(&self);
 }
 
 impl PPrint for Expr {
     fn prettify(&self) -> String {
         match self {
             Expr::Binary(left, op, right) =>
-                format!("( {} {} {} )", op.lexeme, left.prettify(), right.prettify()),
-            Expr::MsgEmission(self_expr, msg_name, arg_opt) => {
-                let mut str;
-                match self_expr {
-                    Some(self_expr) => str = format!("{}.{}", self_expr.prettify(), msg_name.lexeme),
-                    None => str = format!("{}", msg_name.lexeme),
-                }
-                if let Some(arg) = arg_opt {
-                    str.push_str(format!(": {}", arg.prettify()).as_str())
+                format!("{}( {} {} {} )", docs(&op.trivia), op.lexeme, left.prettify(), right.prettify()),
+            Expr::MsgEmission(self_expr, msg_name, args) => {
+                let mut str = match self_expr {
+                    Some(self_expr) => format!("{}.{}{}", self_expr.prettify(), docs(&msg_name.trivia), msg_name.lexeme),
+                    None => format!("{}{}", docs(&msg_name.trivia), msg_name.lexeme),
+                };
+                if !args.is_empty() {
+                    let args: Vec<String> = args.iter().map(|arg| arg.prettify()).collect();
+                    str.push_str(format!(": {}", args.join(", ")).as_str())
                 }
                 str
             },
             Expr::BinaryOpt(left, op, right) => match right {
-                Some(right) => format!("( {} {} {} )", op.lexeme, left.prettify(), right.prettify()),
-                None => format!("( {} {} )", op.lexeme, left.prettify())
+                Some(right) => format!("{}( {} {} {} )", docs(&op.trivia), op.lexeme, left.prettify(), right.prettify()),
+                None => format!("{}( {} {} )", docs(&op.trivia), op.lexeme, left.prettify())
             },
-            Expr::Asm(_, _, text_expr) => {
+            Expr::Asm(_, _, text_expr, _) => {
                 let mut str = "asm ".to_string();
                 str.push_str(text_expr.prettify().as_str());
                 str
             },
-            Expr::Object(exprs) => {
+            Expr::Import(path, _) => format!("import: \"{}\"", path.escape_debug()),
+            Expr::Object(exprs, _) => {
                 let mut str = "[\n".to_string();
                 for expr in exprs {
                     str.push_str(format!("{}\n", expr.prettify()).as_str())
@@ -39,53 +39,90 @@ impl PPrint for Expr {
                 str.push(']');
                 str
             },
-            Expr::CodeBlock(exprs) => {
+            Expr::CodeBlock(exprs, _) => {
                 let mut str: String = "{\n".into();
                 for expr in exprs {
                     str.push_str(format!("{}\n", expr.prettify()).as_str())
                 }
-                str.push_str("}");
+                str.push('}');
                 str
             },
-            Expr::Fn(capture_list, expr) => {
+            Expr::Fn(capture_list, expr, _) => {
                 let mut str = "| ".to_string();
                 for capture in capture_list {
                     str.push_str(format!("{} ", capture.prettify()).as_str())
                 }
                 str.push_str("| ");
-                str.push_str(format!("{}", expr.prettify()).as_str());
+                str.push_str(expr.prettify().as_str());
                 str
             },
-            Expr::Type(exprs) => {
+            Expr::Type(exprs, _) => {
                 let mut str = "t( ".to_string();
                 for expr in exprs {
                     str.push_str(format!("{} ", expr.prettify()).as_str())
                 }
-                str.push_str(")");
+                str.push(')');
                 str
             },
             // Expr::Identifier(name) => format!("{}", name.lexeme),
-            Expr::Literal(inner) => format!("{}", inner.prettify()),
+            Expr::Literal(inner, _) => inner.prettify(),
+        }
+    }
+}
+
+impl PPrint for Module {
+    fn prettify(&self) -> String {
+        let mut str = String::new();
+        for decl in &self.decls {
+            str.push_str(&docs(&decl.docs));
+            if decl.public { str.push_str("pub: ") }
+            str.push_str(format!("{}\n", decl.expr.prettify()).as_str());
         }
+        str
     }
+}
 
-    fn pprint(&self) {
-        println!("{}", self.prettify());
+// doc comments, each on its own line
+fn docs(trivia: &[Token]) -> String {
+    let mut str = String::new();
+    for doc in trivia {
+        if let TokenType::DocComment(text) = &doc.ttype {
+            str.push_str(format!("##{}\n", text).as_str())
+        }
     }
+    str
 }
 
 impl PPrint for Literal {
     fn prettify(&self) -> String {
         match self {
-            Literal::String(val) => format!("\"{}\"", val),
-            Literal::Char(val) => format!("'{}'", val),
+            Literal::String(val, _) => {
+                // bytes that aren't UTF-8 can only have come from `\x` escapes
+                let mut text = String::new();
+                for chunk in val.utf8_chunks() {
+                    text.push_str(&chunk.valid().escape_debug().to_string());
+                    for byte in chunk.invalid() {
+                        text.push_str(&format!("\\x{:02x}", byte));
+                    }
+                }
+                format!("\"{}\"", text)
+            },
+            Literal::Char(val) => format!("'{}'", val.escape_debug()),
             Literal::Integer(val) => format!("{}", val),
-            Literal::Float(val) => format!("{}", val),
+            Literal::Float(val) => format!("{:?}", val),
             Literal::Byte(val) => format!("{}b", val),
+            Literal::B16(val) => format!("{}b16", val),
+            Literal::B32(val) => format!("{}b32", val),
+            Literal::B64(val) => format!("{}b64", val),
+            Literal::Bool(val) => format!("{}", val),
+            Literal::I8(val) => format!("{}i8", val),
+            Literal::I16(val) => format!("{}i16", val),
+            Literal::I64(val) => format!("{}i64", val),
+            Literal::U8(val) => format!("{}u8", val),
+            Literal::U16(val) => format!("{}u16", val),
+            Literal::U32(val) => format!("{}u32", val),
+            Literal::U64(val) => format!("{}u64", val),
+            Literal::F64(val) => format!("{:?}f64", val),
         }
     }
-
-    fn pprint(&self) {
-        println!("{}", self.prettify());
-    }
 }
\ No newline at end of file
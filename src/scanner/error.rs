@@ -1,3 +1,5 @@
-pub enum ScannerError<'a> {
-    Err(usize, &'a str),
+// line, column, message
+#[derive(Debug, Clone)]
+pub enum ScannerError {
+    Err(usize, usize, String),
 }
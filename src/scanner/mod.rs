@@ -1,17 +1,29 @@
 pub mod error;
 
-use crate::error::Error;
-use crate::token::{literal::Literal, Token, TokenType};
-use std::{collections::HashMap, str::FromStr};
+use crate::token::{literal::Literal, span::{SourceMap, Span}, Token, TokenType};
+use std::{collections::HashMap, convert::TryFrom, str::FromStr};
 
 use self::error::ScannerError;
 
+// what an escape sequence stands for. `\x` escapes give a byte, which in a string needn't be UTF-8
+enum Escaped {
+    Char(char),
+    Byte(u8),
+}
+
 pub struct Scanner {
     source: String,
+    // where source begins in its file
+    origin: Span,
 
+    // byte offsets into source; always on a char boundary
     start: usize,
     current: usize,
     line: usize,
+    col: usize,
+    start_line: usize,
+    start_col: usize,
+    errors: Vec<ScannerError>,
 
     sym_keywords: HashMap<String, TokenType>,
     an_keywords: HashMap<String, TokenType>,
@@ -19,11 +31,20 @@ pub struct Scanner {
 
 impl Scanner {
     pub fn new(source: String) -> Self {
+        Self::with_origin(source, Span::new(0, 0, 0, 1, 1))
+    }
+    // scans source as though it were found at origin, e.g. text embedded in an asm string
+    pub fn with_origin(source: String, origin: Span) -> Self {
         Self {
             source,
+            origin,
             start: 0,
             current: 0,
-            line: 1,
+            line: origin.line,
+            col: origin.col,
+            start_line: origin.line,
+            start_col: origin.col,
+            errors: Vec::new(),
             sym_keywords: {
                 let mut map = HashMap::new();
                 map.insert("=".to_string(), TokenType::Equal);
@@ -35,48 +56,37 @@ impl Scanner {
                 map.insert("_".to_string(), TokenType::Underscore);
                 map.insert("Self".to_string(), TokenType::Self_);
                 map.insert("asm".to_string(), TokenType::Asm);
+                map.insert("import".to_string(), TokenType::Import);
+                map.insert("true".to_string(), TokenType::Literal(Literal::Bool(true)));
+                map.insert("false".to_string(), TokenType::Literal(Literal::Bool(false)));
                 map
             },
         }
     }
 
-    pub fn scan_tokens(&mut self) -> Result<Vec<Token>, Error> {
-        let mut tokens = Vec::new();
-        while !self.is_at_end() {
-            match self.scan_token()? {
-                Some(token) => tokens.push(token),
-                None => {}
-            }
-        }
-        tokens.push(self.new_token(TokenType::End));
-
-        Ok(tokens)
-    }
     pub fn scan_tokens_err_ignore(&mut self) -> Vec<Token> {
+        let (mut tokens, _) = self.scan_tokens_recover();
+        tokens.retain(|token| token.ttype != TokenType::Error);
+        tokens
+    }
+    // keeps scanning past lexical errors, replacing each bad lexeme with an Error token
+    pub fn scan_tokens_recover(&mut self) -> (Vec<Token>, Vec<ScannerError>) {
         let mut tokens = Vec::new();
         while !self.is_at_end() {
-            match self.scan_token() {
-                Ok(token_opt) => match token_opt {
-                    Some(token) => tokens.push(token),
-                    None => {}
-                },
-                Err(_) => {}
-            }
+            if let Some(token) = self.scan_token() { tokens.push(token) }
         }
         tokens.push(self.new_token(TokenType::End));
 
-        tokens
+        (tokens, std::mem::take(&mut self.errors))
     }
 
-    fn scan_token(&mut self) -> Result<Option<Token>, ScannerError> {
-        // in jovis you might be able to remove the whole result type by passing the context
-        // of the while loop's block to this function, and instead of returning none it just
-        // returns from that context, basically acting like a `continue` statement.
+    fn scan_token(&mut self) -> Option<Token> {
         let mut c = self.advance();
 
         let t = match c {
             ' ' | '\r' | '\t' => None, // Ignore whitespace
             ':' => Some(self.new_token(TokenType::Colon)),
+            ',' => Some(self.new_token(TokenType::Comma)),
             '.' => Some(self.new_token(TokenType::Period)),
             '{' => Some(self.new_token(TokenType::LeftBrace)),
             '}' => Some(self.new_token(TokenType::RightBrace)),
@@ -88,11 +98,17 @@ impl Scanner {
             '|' => Some(self.new_token(TokenType::Pipe)),
             ';' => Some(self.new_token(TokenType::Semicolon)),
             '\n' => {
-                self.line += 1;
+                self.new_line();
                 None
             }
-            '\'' => self.scan_char()?,
-            '"' => self.scan_string()?,
+            '\'' => Some(self.scan_char()),
+            '"' => Some(if self.peak() == '"' && self.peak_next() == '"' {
+                self.advance();
+                self.advance();
+                self.scan_block_string(0, false)
+            } else {
+                self.scan_string()
+            }),
 
             '-' => Some(if Self::is_digit(self.peak()) {
                 self.advance();
@@ -101,18 +117,34 @@ impl Scanner {
                 self.scan_sym_ident()
             }),
 
-            '#' => {
-                while !self.is_at_end() {
-                    let c = self.advance();
-                    if c == '\n' {
-                        self.line += 1;
-                        break;
+            '#' => match self.peak() {
+                '[' => {
+                    self.skip_block_comment();
+                    None
+                }
+                '#' => {
+                    self.advance();
+                    while self.peak() != '\n' && !self.is_at_end() {
+                        self.advance();
                     }
+                    let text = self.source[(self.start + 2)..self.current].to_string();
+                    Some(self.new_token(TokenType::DocComment(text)))
                 }
-                None
-            }
+                _ => {
+                    while !self.is_at_end() {
+                        let c = self.advance();
+                        if c == '\n' {
+                            self.new_line();
+                            break;
+                        }
+                    }
+                    None
+                }
+            },
 
-            _ => Some(if Self::is_digit(c) {
+            _ => Some(if c == 'r' && self.source[self.current..].trim_start_matches('#').starts_with('"') {
+                self.scan_raw_string()
+            } else if Self::is_digit(c) {
                 self.scan_number()
             } else if c == '_' {
                 loop {
@@ -134,122 +166,472 @@ impl Scanner {
         };
 
         self.start = self.current;
+        self.start_line = self.line;
+        self.start_col = self.col;
 
-        Ok(t)
+        t
     }
 
     fn advance(&mut self) -> char {
-        self.current += 1;
-
-        self.source.chars().nth(self.current - 1).unwrap()
+        match self.source[self.current..].chars().next() {
+            Some(c) => {
+                self.current += c.len_utf8();
+                self.col += 1;
+                c
+            }
+            None => '\0',
+        }
     }
     fn peak(&self) -> char {
-        if self.is_at_end() {
-            '\0'
-        } else {
-            self.source.chars().nth(self.current).unwrap()
-        }
+        self.source[self.current..].chars().next().unwrap_or('\0')
     }
     fn peak_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            '\0'
-        } else {
-            self.source.chars().nth(self.current + 1).unwrap()
-        }
+        let mut chars = self.source[self.current..].chars();
+        chars.next();
+        chars.next().unwrap_or('\0')
     }
 
+    fn lexeme(&self) -> &str {
+        &self.source[self.start..self.current]
+    }
     fn new_token(&self, ttype: TokenType) -> Token {
-        let text = self
-            .source
-            .get(self.start..self.current)
-            .unwrap()
-            .to_string();
-
-        Token::new(ttype, text, self.line, self.start)
-    }
-
-    fn scan_char(&mut self) -> Result<Option<Token>, ScannerError> {
-        let next_char = self.peak();
-        if next_char == '\n' || next_char == '\0' {
-            Err(ScannerError::Err(
-                self.line,
-                "Unterminated character literal",
-            ))
-        } else {
-            let val = self.advance();
-            if self.peak() == '\'' {
+        let span = Span::new(
+            self.origin.file_id,
+            self.origin.start + self.start,
+            self.origin.start + self.current,
+            self.start_line,
+            self.start_col,
+        );
+        Token::new(ttype, self.lexeme().to_string(), span)
+    }
+    // an empty span where the scanner is
+    fn here(&self) -> Span {
+        let offset = self.origin.start + self.current;
+        Span::new(self.origin.file_id, offset, offset, self.line, self.col)
+    }
+    // called with the newline already consumed
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.col = 1;
+    }
+
+    fn scan_char(&mut self) -> Token {
+        let errors = self.errors.len();
+        let val = match self.peak() {
+            '\n' | '\0' => {
+                self.report(self.current, "Unterminated character literal");
+                return self.new_token(TokenType::Error);
+            }
+            '\\' => {
                 self.advance();
-                Ok(Some(self.new_token(TokenType::Literal(Literal::Char(val)))))
-            } else {
-                Err(ScannerError::Err(
-                    self.line,
-                    "Unterminated or oversized character literal",
-                ))
-            } // TODO: keep scanning after one character to see if it's oversized or unterminated
-        }
-    }
-    fn scan_string(&mut self) -> Result<Option<Token>, ScannerError> {
-        let mut next_char = self.peak();
-        while next_char != '"' {
-            if next_char == '\0' {
-                return Err(ScannerError::Err(self.line, "Unterminated string"));
-            } else {
-                if next_char == '\n' {
-                    self.line += 1;
+                self.scan_char_escape()
+            }
+            _ => self.advance(),
+        };
+        if self.peak() != '\'' {
+            // an oversized literal still ends with a quote on the same line
+            match self.source[self.current..].find(['\'', '\n']) {
+                Some(len) if self.source[self.current + len..].starts_with('\'') => {
+                    while self.advance() != '\'' {}
+                    self.report(self.start, "Oversized character literal");
                 }
+                _ => self.report(self.current, "Unterminated character literal"),
+            }
+            return self.new_token(TokenType::Error);
+        }
+        self.advance();
+        if self.errors.len() > errors {
+            return self.new_token(TokenType::Error);
+        }
+        self.new_token(TokenType::Literal(Literal::Char(val)))
+    }
+    fn scan_string(&mut self) -> Token {
+        let errors = self.errors.len();
+        let mut val = vec![];
+        let mut map = SourceMap::default();
+        map.add(0, self.here());
+        loop {
+            match self.peak() {
+                '"' => break,
+                '\0' if self.is_at_end() => {
+                    self.report(self.start, "Unterminated string");
+                    return self.new_token(TokenType::Error);
+                }
+                '\\' => {
+                    map.add(val.len(), self.here());
+                    self.advance();
+                    self.scan_escape(&mut val);
+                    map.add(val.len(), self.here());
+                }
+                '\n' => {
+                    push_char(&mut val, self.advance());
+                    self.new_line();
+                }
+                _ => {
+                    let c = self.advance();
+                    push_char(&mut val, c);
+                }
+            }
+        }
+
+        self.advance();
+        if self.errors.len() > errors {
+            return self.new_token(TokenType::Error);
+        }
+        self.new_token(TokenType::Literal(Literal::String(val, map)))
+    }
+    // raw strings are written r"...", or r#"..."# when they contain quotes, and have no escapes
+    fn scan_raw_string(&mut self) -> Token {
+        let mut hashes = 0;
+        while self.peak() == '#' {
+            self.advance();
+            hashes += 1;
+        }
+        self.advance();
+        if self.peak() == '"' && self.peak_next() == '"' {
+            self.advance();
+            self.advance();
+            return self.scan_block_string(hashes, true);
+        }
+
+        let closing = format!("\"{}", "#".repeat(hashes));
+        let content_start = self.current;
+        let mut map = SourceMap::default();
+        map.add(0, self.here());
+        while !self.source[self.current..].starts_with(&closing) {
+            if self.is_at_end() {
+                self.report(self.start, "Unterminated raw string");
+                return self.new_token(TokenType::Error);
+            }
+            if self.advance() == '\n' {
+                self.new_line();
+            }
+        }
+        let val = self.source.as_bytes()[content_start..self.current].to_vec();
+        for _ in 0..closing.len() {
+            self.advance();
+        }
+        self.new_token(TokenType::Literal(Literal::String(val, map)))
+    }
+    // block strings start with a new line after the opening `"""`, and the indentation of the
+    // closing `"""` is removed from every line. called with the opening quotes already consumed
+    fn scan_block_string(&mut self, hashes: usize, raw: bool) -> Token {
+        let errors = self.errors.len();
+        let closing = format!("\"\"\"{}", "#".repeat(hashes));
+        if self.peak() == '\n' {
+            self.advance();
+            self.new_line();
+        } else {
+            self.report(self.current, "Expected a new line after opening '\"\"\"'");
+        }
+
+        // offset of each line, its text, how much of it is leading whitespace, and where its text
+        // starts in the source, then its text after each escape
+        let mut lines = vec![(self.current, vec![], 0, vec![(0, self.here())])];
+        let mut leading = true;
+        while !self.source[self.current..].starts_with(&closing) {
+            if self.is_at_end() {
+                self.report(self.start, "Unterminated string");
+                return self.new_token(TokenType::Error);
+            }
+            let c = self.peak();
+            if c == '\n' {
+                self.advance();
+                self.new_line();
+                lines.push((self.current, vec![], 0, vec![(0, self.here())]));
+                leading = true;
+                continue;
+            }
+            let mut val = vec![];
+            if c == '\\' && !raw {
+                let escape = self.here();
                 self.advance();
-                next_char = self.peak();
+                leading = false;
+                self.scan_escape(&mut val);
+                let line = lines.last_mut().unwrap();
+                line.3.push((line.1.len(), escape));
+                line.3.push((line.1.len() + val.len(), self.here()));
+            } else {
+                leading = leading && (c == ' ' || c == '\t');
+                push_char(&mut val, self.advance());
+            }
+            let line = lines.last_mut().unwrap();
+            line.1.append(&mut val);
+            if leading {
+                line.2 += 1;
+            }
+        }
+        for _ in 0..closing.len() {
+            self.advance();
+        }
+
+        let (closing_offset, indent, indent_len, _) = lines.pop().unwrap();
+        if indent_len != indent.len() {
+            self.report(closing_offset, "Closing '\"\"\"' must be on its own line");
+        }
+        let mut val = vec![];
+        let mut map = SourceMap::default();
+        for (i, (offset, text, leading_len, starts)) in lines.iter().enumerate() {
+            if i > 0 {
+                val.push(b'\n');
+            }
+            if *leading_len == text.len() {
+                continue; // blank lines don't need to be indented
+            }
+            if *leading_len < indent.len() || !text.starts_with(&indent) {
+                self.report(*offset, "Insufficient indentation in multi-line string");
+                continue;
+            }
+            // the indentation is whitespace, so it comes before any escape
+            let (_, line_start) = starts[0];
+            map.add(val.len(), line_start.shifted(std::str::from_utf8(&indent).unwrap()));
+            for (start, span) in &starts[1..] {
+                map.add(val.len() + start - indent.len(), *span);
             }
+            val.extend_from_slice(&text[indent.len()..]);
         }
 
-        let val = self
-            .source
-            .get((self.start + 1)..self.current)
-            .unwrap()
-            .to_string();
+        if self.errors.len() > errors {
+            return self.new_token(TokenType::Error);
+        }
+        self.new_token(TokenType::Literal(Literal::String(val, map)))
+    }
+    // block comments are delimited by `#[` and `]#`, and nest
+    // called with the first `#` already consumed
+    fn skip_block_comment(&mut self) {
         self.advance();
-        Ok(Some(
-            self.new_token(TokenType::Literal(Literal::String(val))),
-        ))
+        // offsets of the comments still open, innermost last
+        let mut openings = vec![self.start];
+        loop {
+            if self.is_at_end() {
+                let opening = openings.pop().unwrap();
+                self.report(opening, "Unterminated block comment");
+                return;
+            }
+            match self.advance() {
+                '#' if self.peak() == '[' => {
+                    openings.push(self.current - 1);
+                    self.advance();
+                }
+                ']' if self.peak() == '#' => {
+                    self.advance();
+                    openings.pop();
+                    if openings.is_empty() {
+                        return;
+                    }
+                }
+                '\n' => self.new_line(),
+                _ => {}
+            }
+        }
+    }
+    // adds what an escape in a string stands for to val. called with the backslash already consumed
+    fn scan_escape(&mut self, val: &mut Vec<u8>) {
+        match self.escape_value() {
+            Ok(Escaped::Char(c)) => push_char(val, c),
+            Ok(Escaped::Byte(byte)) => val.push(byte),
+            Err(e) => self.errors.push(e),
+        }
     }
+    // a char literal has to be a char, so its `\x` escapes are only ASCII
+    fn scan_char_escape(&mut self) -> char {
+        let escape_start = self.current - 1;
+        match self.escape_value() {
+            Ok(Escaped::Char(c)) => c,
+            Ok(Escaped::Byte(byte)) if byte.is_ascii() => byte as char,
+            Ok(Escaped::Byte(_)) => {
+                self.report(escape_start, "'\\x' escape in a character literal must be at most 0x7F, use '\\u{...}' instead");
+                char::REPLACEMENT_CHARACTER
+            }
+            Err(e) => {
+                self.errors.push(e);
+                char::REPLACEMENT_CHARACTER
+            }
+        }
+    }
+    fn escape_value(&mut self) -> Result<Escaped, ScannerError> {
+        let escape_start = self.current - 1;
+        let c = match self.advance() {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '\'' => '\'',
+            'x' => {
+                let digits_start = self.current;
+                for _ in 0..2 {
+                    if !self.peak().is_ascii_hexdigit() {
+                        return Err(self.error(self.current, "Expected two hex digits in '\\x' escape"));
+                    }
+                    self.advance();
+                }
+                let val = u8::from_str_radix(&self.source[digits_start..self.current], 16).unwrap();
+                return Ok(Escaped::Byte(val));
+            }
+            'u' => {
+                if self.peak() != '{' {
+                    return Err(self.error(self.current, "Expected '{' in '\\u' escape"));
+                }
+                self.advance();
+                let digits_start = self.current;
+                while self.peak().is_ascii_hexdigit() {
+                    self.advance();
+                }
+                let digits = &self.source[digits_start..self.current];
+                if digits.is_empty() || digits.len() > 6 {
+                    return Err(self.error(digits_start, "Expected 1 to 6 hex digits in '\\u' escape"));
+                }
+                if self.peak() != '}' {
+                    return Err(self.error(self.current, "Expected '}' in '\\u' escape"));
+                }
+                let val = u32::from_str_radix(digits, 16).unwrap();
+                self.advance();
+                match char::from_u32(val) {
+                    Some(c) => c,
+                    None => return Err(self.error(escape_start, "'\\u' escape is not a valid unicode scalar value")),
+                }
+            }
+            '\0' if self.is_at_end() => return Err(self.error(escape_start, "Unterminated escape sequence")),
+            _ => return Err(self.error(escape_start, "Invalid escape sequence")),
+        };
+        Ok(Escaped::Char(c))
+    }
+    // called with the first digit (and sign, if any) already consumed
     fn scan_number(&mut self) -> Token {
-        while Self::is_digit(self.peak()) {
-            self.advance();
+        match self.number_value() {
+            Ok(literal) => self.new_token(TokenType::Literal(literal)),
+            Err(e) => {
+                self.errors.push(e);
+                self.new_token(TokenType::Error)
+            }
         }
-        if self.peak() == '.' && Self::is_digit(self.peak_next()) {
+    }
+    fn number_value(&mut self) -> Result<Literal, ScannerError> {
+        let negative = self.lexeme().starts_with('-');
+        let mut radix = 10;
+        let mut digits_start = self.current - 1;
+        if self.lexeme().ends_with('0') {
+            let prefix_radix = match self.peak() {
+                'x' => 16,
+                'o' => 8,
+                'b' => 2,
+                _ => 10,
+            };
+            // `0b` on its own is still the byte literal 0, and so is `0b16` zero as a B16
+            let suffix: String = self.source[self.current..].chars().take_while(|c| Self::is_alpha_numeric(*c)).collect();
+            let is_suffix = matches!(suffix.as_str(), "b16" | "b32" | "b64");
+            if prefix_radix != 10 && self.peak_next().is_digit(prefix_radix) && !is_suffix {
+                radix = prefix_radix;
+                self.advance();
+                digits_start = self.current;
+            }
+        }
+        while self.peak().is_digit(radix) || self.peak() == '_' {
             self.advance();
-            while Self::is_digit(self.peak()) {
+        }
+
+        let mut is_float = false;
+        if radix == 10 {
+            if self.peak() == '.' && Self::is_digit(self.peak_next()) {
+                is_float = true;
                 self.advance();
+                while Self::is_digit(self.peak()) || self.peak() == '_' {
+                    self.advance();
+                }
+            }
+            let mut exponent = self.source[self.current..].chars();
+            if let Some('e' | 'E') = exponent.next() {
+                let has_exponent = match exponent.next() {
+                    Some('+' | '-') => exponent.next().is_some_and(Self::is_digit),
+                    Some(c) => Self::is_digit(c),
+                    None => false,
+                };
+                if has_exponent {
+                    is_float = true;
+                    self.advance();
+                    self.advance();
+                    while Self::is_digit(self.peak()) || self.peak() == '_' {
+                        self.advance();
+                    }
+                }
             }
+        }
 
-            let text = self.source.get(self.start..self.current).unwrap();
-            self.new_token(TokenType::Literal(Literal::Float(
-                f32::from_str(text).unwrap(),
-            )))
-        } else if self.peak() == 'b' {
-            let src_copy = self.source.clone();
-            let text = src_copy.get(self.start..self.current).unwrap();
+        let mut digits: String = self.source[digits_start..self.current]
+            .chars()
+            .filter(|c| *c != '_')
+            .collect();
+        if negative {
+            digits.insert(0, '-');
+        }
+
+        let suffix_start = self.current;
+        while Self::is_alpha_numeric(self.peak()) {
             self.advance();
-            self.new_token(TokenType::Literal(Literal::Byte(
-                u8::from_str(text).unwrap(),
-            )))
-        } else {
-            let text = self.source.get(self.start..self.current).unwrap();
-            self.new_token(TokenType::Literal(Literal::Integer(
-                i32::from_str(text).unwrap(),
-            )))
         }
+        let suffix = &self.source[suffix_start..self.current];
+
+        let literal = if is_float || suffix == "f32" || suffix == "f64" {
+            if radix != 10 {
+                return Err(self.error(self.start, "Float literals must be decimal"));
+            }
+            let val = f64::from_str(&digits).unwrap();
+            match suffix {
+                "" | "f32" => {
+                    let val = val as f32;
+                    if val.is_infinite() {
+                        return Err(self.error(self.start, "Float literal out of range for F32"));
+                    }
+                    Literal::Float(val)
+                }
+                "f64" => {
+                    if val.is_infinite() {
+                        return Err(self.error(self.start, "Float literal out of range for F64"));
+                    }
+                    Literal::F64(val)
+                }
+                _ => return Err(self.error(suffix_start, "Invalid suffix for float literal")),
+            }
+        } else {
+            let val = match i128::from_str_radix(&digits, radix) {
+                Ok(val) => val,
+                Err(_) => return Err(self.error(self.start, "Integer literal out of range")),
+            };
+            let (literal, type_name) = match suffix {
+                "" | "i32" => (i32::try_from(val).ok().map(Literal::Integer), "I32"),
+                "b" => (u8::try_from(val).ok().map(Literal::Byte), "B8"),
+                "b16" => (u16::try_from(val).ok().map(Literal::B16), "B16"),
+                "b32" => (u32::try_from(val).ok().map(Literal::B32), "B32"),
+                "b64" => (u64::try_from(val).ok().map(Literal::B64), "B64"),
+                "i8" => (i8::try_from(val).ok().map(Literal::I8), "I8"),
+                "i16" => (i16::try_from(val).ok().map(Literal::I16), "I16"),
+                "i64" => (i64::try_from(val).ok().map(Literal::I64), "I64"),
+                "u8" => (u8::try_from(val).ok().map(Literal::U8), "U8"),
+                "u16" => (u16::try_from(val).ok().map(Literal::U16), "U16"),
+                "u32" => (u32::try_from(val).ok().map(Literal::U32), "U32"),
+                "u64" => (u64::try_from(val).ok().map(Literal::U64), "U64"),
+                _ => return Err(self.error(suffix_start, "Invalid suffix for integer literal")),
+            };
+            match literal {
+                Some(literal) => literal,
+                None => {
+                    return Err(self.error(
+                        self.start,
+                        &format!("Integer literal out of range for {}", type_name),
+                    ))
+                }
+            }
+        };
+        Ok(literal)
     }
     fn scan_an_ident(&mut self) -> Token {
         while Self::is_alpha_numeric(self.peak()) {
             self.advance();
         }
 
-        let text = self
-            .source
-            .get(self.start..self.current)
-            .unwrap()
-            .to_string();
+        let text = self.lexeme().to_string();
         let ttype = self
             .an_keywords
             .get(&text)
@@ -262,11 +644,7 @@ impl Scanner {
             self.advance();
         }
 
-        let text = self
-            .source
-            .get(self.start..self.current)
-            .unwrap()
-            .to_string();
+        let text = self.lexeme().to_string();
 
         let ttype = self
             .sym_keywords
@@ -276,16 +654,26 @@ impl Scanner {
         self.new_token(ttype)
     }
 
+    fn report(&mut self, offset: usize, message: &str) {
+        let error = self.error(offset, message);
+        self.errors.push(error);
+    }
+    // offset must not be past current; columns are counted in chars, starting at 1
+    fn error(&self, offset: usize, message: &str) -> ScannerError {
+        let line = self.line - self.source[offset..self.current].matches('\n').count();
+        let col = match self.source[..offset].rfind('\n') {
+            Some(i) => self.source[(i + 1)..offset].chars().count() + 1,
+            None => self.source[..offset].chars().count() + self.origin.col,
+        };
+        ScannerError::Err(line, col, message.to_string())
+    }
+
     fn is_sym(c: char) -> bool {
         match c {
-            ' ' | '\r' | '\t' | ':' | '#' | '.' | '{' | '}' | '(' | ')' | '[' | ']' | '\n'
+            ' ' | '\r' | '\t' | ':' | ',' | '#' | '.' | '{' | '}' | '(' | ')' | '[' | ']' | '\n'
             | '\'' | '"' | '\0' => false,
             _ => {
-                if (c < 'a' || c > 'z') && (c < 'A' || c > 'Z') && (c < '0' || c > '9') {
-                    true
-                } else {
-                    false
-                }
+                !c.is_ascii_alphanumeric()
             }
         }
     }
@@ -295,14 +683,182 @@ impl Scanner {
     }
 
     fn is_alpha(c: char) -> bool {
-        (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '_'
+        c.is_ascii_alphabetic() || c == '_'
     }
 
     fn is_digit(c: char) -> bool {
-        c >= '0' && c <= '9'
+        c.is_ascii_digit()
     }
 
     fn is_alpha_numeric(c: char) -> bool {
         Self::is_alpha(c) || Self::is_digit(c)
     }
 }
+
+fn push_char(val: &mut Vec<u8>, c: char) {
+    val.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Scanner;
+    use crate::{error::Error, token::{Token, TokenType, literal::Literal}};
+
+    fn scan(source: &str) -> Vec<Token> {
+        let (mut tokens, errors) = Scanner::new(source.to_string()).scan_tokens_recover();
+        assert!(errors.is_empty(), "{}: {}", source, Error::from(errors[0].clone()));
+        tokens.pop(); // End
+        tokens
+    }
+    fn literal(source: &str) -> Literal {
+        match scan(source).remove(0).ttype {
+            TokenType::Literal(literal) => literal,
+            ttype => panic!("{}: expected a literal, found {:?}", source, ttype),
+        }
+    }
+    // each error as "message at line:col"
+    fn errors(source: &str) -> Vec<String> {
+        let (_, errors) = Scanner::new(source.to_string()).scan_tokens_recover();
+        errors.into_iter().map(|e| Error::from(e).to_string()).collect()
+    }
+
+    #[test]
+    fn utf8_source() {
+        // offsets are in bytes, columns in chars
+        let spans: Vec<(String, usize, usize, usize)> = scan("x = \"héllo\" ∘ y").into_iter()
+            .map(|t| (t.lexeme, t.span.start, t.span.end, t.span.col)).collect();
+        let expected = [("x", 0, 1, 1), ("=", 2, 3, 3), ("\"héllo\"", 4, 12, 5), ("∘", 13, 16, 13), ("y", 17, 18, 15)];
+        assert_eq!(spans, expected.iter().map(|(l, s, e, c)| (l.to_string(), *s, *e, *c)).collect::<Vec<_>>());
+        assert_eq!(errors("\"é\" '\\q'"), vec!["Invalid escape sequence at 1:6"]);
+        assert_eq!(errors("ü\n  'ab'"), vec!["Oversized character literal at 2:3"]);
+    }
+
+    #[test]
+    fn escapes() {
+        let strings: [(&str, &[u8]); 6] = [
+            (r#""a\n\t\r\\\"\'""#, b"a\n\t\r\\\"'"),
+            (r#""\x00""#, b"\x00"),
+            (r#""\xff""#, b"\xff"),
+            (r#""\x41\x7F\x80""#, b"A\x7f\x80"),
+            (r#""\u{e9}\u{1F600}""#, "é😀".as_bytes()),
+            (r#""\0 é""#, "\0 é".as_bytes()),
+        ];
+        for (source, bytes) in strings.iter() {
+            match literal(source) {
+                Literal::String(val, _) => assert_eq!(&val, bytes, "{}", source),
+                literal => panic!("{}: expected a string, found {:?}", source, literal),
+            }
+        }
+        assert_eq!(literal(r"'\x41'"), Literal::Char('A'));
+        assert_eq!(literal(r"'\u{1F600}'"), Literal::Char('😀'));
+        assert_eq!(literal(r"'\''"), Literal::Char('\''));
+
+        assert_eq!(errors(r#""\q""#), vec!["Invalid escape sequence at 1:2"]);
+        assert_eq!(errors(r#""\x4""#), vec![r"Expected two hex digits in '\x' escape at 1:5"]);
+        assert_eq!(errors(r#""\u{110000}""#), vec![r"'\u' escape is not a valid unicode scalar value at 1:2"]);
+        assert_eq!(errors(r#""\u{}""#), vec![r"Expected 1 to 6 hex digits in '\u' escape at 1:5"]);
+        assert_eq!(errors(r"'\xff'"), vec![r"'\x' escape in a character literal must be at most 0x7F, use '\u{...}' instead at 1:2"]);
+        // every bad escape is reported
+        assert_eq!(errors(r#""\q \w""#).len(), 2);
+    }
+
+    #[test]
+    fn numbers() {
+        let cases = [
+            ("42", Literal::Integer(42)),
+            ("-42", Literal::Integer(-42)),
+            ("1_000_000", Literal::Integer(1_000_000)),
+            ("0x1F", Literal::Integer(0x1F)),
+            ("-0x10", Literal::Integer(-16)),
+            ("0o17", Literal::Integer(0o17)),
+            ("0b101", Literal::Integer(0b101)),
+            ("0x1f32", Literal::Integer(0x1f32)),
+            ("0b", Literal::Byte(0)),
+            ("0b16", Literal::B16(0)),
+            ("0b32", Literal::B32(0)),
+            ("0b64", Literal::B64(0)),
+            ("0b10b16", Literal::B16(0b10)),
+            ("0b1b", Literal::Byte(1)),
+            ("0o377b", Literal::Byte(0xFF)),
+            ("255u8", Literal::U8(255)),
+            ("-128i8", Literal::I8(-128)),
+            ("0xFFFF_FFFFu32", Literal::U32(0xFFFF_FFFF)),
+            ("7i64", Literal::I64(7)),
+            ("1b64", Literal::B64(1)),
+            ("1.5", Literal::Float(1.5)),
+            ("1e3", Literal::Float(1000.0)),
+            ("2.5e-1f64", Literal::F64(0.25)),
+            ("1f64", Literal::F64(1.0)),
+        ];
+        for (source, expected) in cases.iter() {
+            assert_eq!(&literal(source), expected, "{}", source);
+        }
+
+        assert_eq!(errors("256u8"), vec!["Integer literal out of range for U8 at 1:1"]);
+        assert_eq!(errors("2147483648"), vec!["Integer literal out of range for I32 at 1:1"]);
+        assert_eq!(errors("1x"), vec!["Invalid suffix for integer literal at 1:2"]);
+        assert_eq!(errors("0o8"), vec!["Invalid suffix for integer literal at 1:2"]);
+        assert_eq!(errors("1.0i32"), vec!["Invalid suffix for float literal at 1:4"]);
+        assert_eq!(errors("1e999f64"), vec!["Float literal out of range for F64 at 1:1"]);
+        assert_eq!(errors("0x1f32 0b1f32"), vec!["Float literals must be decimal at 1:8"]);
+    }
+
+    #[test]
+    fn recovery() {
+        // each bad lexeme becomes an Error token, and what comes after it is still scanned
+        let source = "a '\\q' 256u8 'bc' b\n\"open";
+        let (tokens, _) = Scanner::new(source.to_string()).scan_tokens_recover();
+        let ttypes: Vec<TokenType> = tokens.into_iter().map(|t| t.ttype).collect();
+        assert_eq!(ttypes, vec![
+            TokenType::Identifier, TokenType::Error, TokenType::Error, TokenType::Error, TokenType::Identifier,
+            TokenType::Error, TokenType::End,
+        ]);
+        assert_eq!(errors(source), vec![
+            "Invalid escape sequence at 1:4",
+            "Integer literal out of range for U8 at 1:8",
+            "Oversized character literal at 1:14",
+            "Unterminated string at 2:1",
+        ]);
+        let lexemes: Vec<String> = Scanner::new(source.to_string()).scan_tokens_err_ignore().into_iter()
+            .map(|t| t.lexeme).collect();
+        assert_eq!(lexemes, vec!["a", "b", ""]);
+    }
+
+    #[test]
+    fn comments() {
+        let ttypes = |source: &str| -> Vec<TokenType> { scan(source).into_iter().map(|t| t.ttype).collect() };
+        assert_eq!(ttypes("a # b\nc"), vec![TokenType::Identifier, TokenType::Identifier]);
+        assert_eq!(ttypes("a #[ b #[ c ]# d ]# e"), vec![TokenType::Identifier, TokenType::Identifier]);
+        assert_eq!(ttypes("#[\n]# a"), vec![TokenType::Identifier]);
+        assert_eq!(ttypes("## doc\na ##\n"), vec![
+            TokenType::DocComment(" doc".into()), TokenType::Identifier, TokenType::DocComment("".into()),
+        ]);
+        // a block comment counts the lines it spans
+        assert_eq!(scan("#[\n\n]# a")[0].span.line, 3);
+
+        assert_eq!(errors("a #[ b #[ c ]#"), vec!["Unterminated block comment at 1:3"]);
+        assert_eq!(errors("#[ #[ ]# ]# a #["), vec!["Unterminated block comment at 1:15"]);
+    }
+
+    #[test]
+    fn string_source_maps() {
+        // (source, offset into the value, where that byte is written)
+        let cases = [
+            ("\"ab\"", 1, (1, 3)),
+            ("x \"a\\tb\"", 2, (1, 7)),
+            ("\"\\u{1F600}b\"", 4, (1, 11)),
+            ("\"a\nb\"", 2, (2, 1)),
+            ("r#\"a\\tb\"#", 3, (1, 7)),
+            ("\"\"\"\n    ab\n      \\tc\n    \"\"\"", 6, (3, 9)),
+            ("\"\"\"\n    ab\n      \\tc\n    \"\"\"", 3, (3, 5)),
+        ];
+        for (source, offset, (line, col)) in cases.iter() {
+            let (val, map) = match scan(source).pop().unwrap().ttype {
+                TokenType::Literal(Literal::String(val, map)) => (val, map),
+                ttype => panic!("{}: expected a string, found {:?}", source, ttype),
+            };
+            let span = map.locate(&val, *offset).unwrap();
+            assert_eq!((span.line, span.col), (*line, *col), "{} at {}", source, offset);
+        }
+    }
+}
@@ -1,8 +1,24 @@
+use super::span::SourceMap;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Literal {
-    String(String),
+    // bytes rather than text, since a `\x` escape can be any byte
+    String(Vec<u8>, SourceMap),
     Char(char),
     Integer(i32),
     Float(f32),
-    Byte(u8)
+    Byte(u8),
+    B16(u16),
+    B32(u32),
+    B64(u64),
+    Bool(bool),
+    // suffixed numeric literals
+    I8(i8),
+    I16(i16),
+    I64(i64),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F64(f64),
 }
\ No newline at end of file
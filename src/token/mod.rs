@@ -1,30 +1,25 @@
 pub mod literal;
+pub mod span;
 
 use literal::Literal;
+use span::Span;
 
 #[derive(Debug, Clone)]
 pub struct Token {
     pub ttype: TokenType,
     pub lexeme: String,
-    pub line: usize,
-    pub start: usize,
+    pub span: Span,
+    // doc comments directly preceding this token
+    pub trivia: Vec<Token>,
 }
 
 impl Token {
-    pub fn new(ttype: TokenType, lexeme: String, line: usize, start: usize) -> Self {
+    pub fn new(ttype: TokenType, lexeme: String, span: Span) -> Self {
         Self {
             ttype,
             lexeme,
-            line,
-            start,
-        }
-    }
-
-    pub fn to_string(&self) -> String {
-        match &self.ttype {
-            TokenType::Identifier => format!("{:?} {} ln{}", self.ttype, self.lexeme, self.line),
-            TokenType::Literal(lit) => format!("{:?} ln{}", lit, self.line),
-            _ => format!("{:?} ln{}", self.ttype, self.line),
+            span,
+            trivia: Vec::new(),
         }
     }
 }
@@ -33,6 +28,7 @@ impl Token {
 pub enum TokenType {
     // Reserved symbols
     Colon,
+    Comma,
     Period,
     LeftBrace,
     RightBrace,
@@ -51,11 +47,16 @@ pub enum TokenType {
     Underscore,
     Self_,
     Asm,
+    Import,
 
     // Literals
     Literal(Literal),
+    // text of a `##` comment, without the leading `##`
+    DocComment(String),
     // Index(u32),
     Identifier,
     // ScopedIdent,
+    // stands in for a lexeme the scanner could not make sense of
+    Error,
     End,
 }
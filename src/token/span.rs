@@ -0,0 +1,69 @@
+use std::fmt::Display;
+
+// a range of source text. offsets are in bytes, line and col count from 1
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub file_id: usize,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Span {
+    pub fn new(file_id: usize, start: usize, end: usize, line: usize, col: usize) -> Self {
+        Self {
+            file_id,
+            start,
+            end,
+            line,
+            col,
+        }
+    }
+
+    // span from the start of self to the end of other
+    pub fn to(&self, other: &Span) -> Self {
+        Self {
+            end: self.end.max(other.end),
+            ..*self
+        }
+    }
+    // empty span just past `skipped`, which must be the text starting at self
+    pub fn shifted(&self, skipped: &str) -> Self {
+        let start = self.start + skipped.len();
+        let (line, col) = match skipped.rfind('\n') {
+            Some(i) => (
+                self.line + skipped.matches('\n').count(),
+                skipped[(i + 1)..].chars().count() + 1,
+            ),
+            None => (self.line, self.col + skipped.chars().count()),
+        };
+        Self::new(self.file_id, start, start, line, col)
+    }
+}
+
+// where the bytes of a string literal's value are in the source, which their offsets in it don't
+// say once an escape or stripped indentation is passed. each entry is an offset into the value,
+// and the span of the source text it starts at
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SourceMap(Vec<(usize, Span)>);
+
+impl SourceMap {
+    // entries have to be added in order
+    pub fn add(&mut self, offset: usize, span: Span) {
+        self.0.push((offset, span));
+    }
+    // where the byte at offset in value is in the source, if value was written out in it
+    pub fn locate(&self, value: &[u8], offset: usize) -> Option<Span> {
+        let (start, span) = self.0.iter().rev().find(|(start, _)| *start <= offset)?;
+        // nothing between entries is escaped, so it's what was written
+        let skipped = std::str::from_utf8(&value[*start..offset]).ok()?;
+        Some(span.shifted(skipped))
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
\ No newline at end of file